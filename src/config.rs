@@ -0,0 +1,45 @@
+use serde::{Deserialize, Serialize};
+
+use crate::models::Card;
+use crate::rules::card::DECK_COMPOSITION;
+
+/// Rule constants of the game. The default matches the standard rules, other values can be used
+/// to test house rules.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GameConfig {
+  pub starting_coins: u16,
+  // Number of rounds at the start of the game where players only buy
+  pub buy_only_turns: usize,
+  pub landmarks_to_win: usize,
+  // Number of unique face-up cards in each of the card markets
+  pub face_up_cards: usize,
+  pub face_up_landmarks: usize,
+  pub min_players: usize,
+  pub max_players: usize,
+  // Coins from the bank for a player with no coins at the start of the buy phase (0 disables)
+  pub coins_when_broke: u16,
+  // Game ends in a draw after this many rounds (0 disables)
+  pub max_rounds: usize,
+  // Game ends in a draw if nobody builds anything for this many rounds (0 disables)
+  pub stalemate_rounds: usize,
+  // (card, copies in the deck)
+  pub deck_composition: Vec<(Card, u8)>,
+}
+
+impl Default for GameConfig {
+  fn default() -> Self {
+    Self {
+      starting_coins: 5,
+      buy_only_turns: 3,
+      landmarks_to_win: 3,
+      face_up_cards: 5,
+      face_up_landmarks: 5,
+      min_players: 2,
+      max_players: 4,
+      coins_when_broke: 1,
+      max_rounds: 200,
+      stalemate_rounds: 50,
+      deck_composition: DECK_COMPOSITION.to_vec(),
+    }
+  }
+}
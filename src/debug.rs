@@ -0,0 +1,198 @@
+use colored::Colorize;
+
+use crate::events::{CoinSource, GameEvent, GameObserver};
+use crate::game::{Game, GameOutcome};
+use crate::models::player::{OwnedCard, OwnedLandmark};
+use crate::models::{Card, CardColor};
+use crate::simulation::SimulationResult;
+
+/// Observer that pretty prints the game to the console as it is played
+pub struct DebugPrinter;
+
+impl Default for DebugPrinter {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl DebugPrinter {
+  pub fn new() -> Self {
+    Self {}
+  }
+}
+
+impl GameObserver for DebugPrinter {
+  fn on_event(&mut self, event: &GameEvent, game: &Game) {
+    match event {
+      GameEvent::TurnStarted {
+        player_index,
+        round,
+      } => {
+        println!(
+          "{}",
+          format!("=== Round {} - Player {} ===", round, player_index)
+            .bold()
+            .cyan()
+        );
+      }
+      GameEvent::DiceRolled { dice, .. } => match dice {
+        (die, 0) => println!("Rolled {}", die.to_string().bold()),
+        (first, second) => println!(
+          "Rolled {} + {} = {}",
+          first,
+          second,
+          (first + second).to_string().bold()
+        ),
+      },
+      GameEvent::CardActivated {
+        card,
+        owner_index,
+        coin_changes,
+      } => {
+        println!(
+          "  {} of player {} activated{}",
+          colored_card_name(*card),
+          owner_index,
+          format_coin_changes(coin_changes)
+        );
+      }
+      GameEvent::LandmarkTriggered {
+        landmark,
+        coin_changes,
+        ..
+      } => {
+        println!(
+          "  {} triggered{}",
+          landmark.def().name.bold().yellow(),
+          format_coin_changes(coin_changes)
+        );
+      }
+      GameEvent::BankTransfer {
+        player_index,
+        coins,
+        source: CoinSource::BankPayout,
+      } => {
+        println!(
+          "  Player {} gets {} from the bank",
+          player_index,
+          format!("{}C", coins).yellow()
+        );
+      }
+      // Shown with the coin changes of the card or landmark that moved them
+      GameEvent::CoinTransfer { .. } | GameEvent::BankTransfer { .. } => {}
+      GameEvent::Purchase { decision, .. } => println!("{}", decision.to_string().bold()),
+      GameEvent::EstablishmentExchanged {
+        player_index,
+        card,
+        opponent_index,
+        opponent_card,
+      } => {
+        println!(
+          "  Player {} exchanged {} for {} of player {}",
+          player_index,
+          colored_card_name(*card),
+          colored_card_name(*opponent_card),
+          opponent_index
+        );
+      }
+      GameEvent::EstablishmentGiven {
+        player_index,
+        to_index,
+        card,
+      } => {
+        println!(
+          "  Player {} gave {} to player {}",
+          player_index,
+          colored_card_name(*card),
+          to_index
+        );
+      }
+      GameEvent::ExtraTurn { player_index } => {
+        println!("  Player {} takes another turn", player_index);
+      }
+      GameEvent::TurnEnded { .. } => print_game(game),
+      GameEvent::GameEnded { outcome } => match outcome {
+        GameOutcome::Win(winner) => {
+          println!("{}", format!("Player {} wins!", winner).bold().green())
+        }
+        GameOutcome::Draw(reason) => {
+          println!("{}", format!("Draw ({:?})", reason).bold().yellow())
+        }
+      },
+    }
+  }
+}
+
+fn colored_card_name(card: Card) -> String {
+  let name = card.def().name;
+  match card.def().color {
+    CardColor::Blue => name.blue().to_string(),
+    CardColor::Green => name.green().to_string(),
+    CardColor::Purple => name.purple().to_string(),
+    CardColor::Red => name.red().to_string(),
+  }
+}
+
+fn format_coin_changes(coin_changes: &[i32]) -> String {
+  let changes = coin_changes
+    .iter()
+    .enumerate()
+    .filter(|(_, change)| **change != 0)
+    .map(|(index, change)| {
+      let change = format!("P{} {:+}", index, change);
+      if change.contains('+') {
+        change.green().to_string()
+      } else {
+        change.red().to_string()
+      }
+    })
+    .collect::<Vec<_>>();
+  if changes.is_empty() {
+    String::new()
+  } else {
+    format!(" ({})", changes.join(", "))
+  }
+}
+
+/// Prints every player's coins, landmarks and cards
+pub fn print_game(game: &Game) {
+  for (index, player) in game.players.iter().enumerate() {
+    let marker = if index == game.current_player {
+      ">"
+    } else {
+      " "
+    };
+    let landmarks = player
+      .landmarks
+      .iter()
+      .map(|OwnedLandmark { landmark, .. }| landmark.def().name)
+      .collect::<Vec<_>>()
+      .join(", ");
+    let cards = player
+      .cards
+      .iter()
+      .map(|OwnedCard { card, .. }| colored_card_name(*card))
+      .collect::<Vec<_>>()
+      .join(", ");
+    println!(
+      "{} Player {} | {} coins | landmarks: [{}] | cards: [{}]",
+      marker,
+      index,
+      player.coins.to_string().yellow(),
+      landmarks.bold(),
+      cards
+    );
+  }
+}
+
+/// Prints the card statistics of a simulation, sorted by the difference between presence in
+/// winning and losing tableaus
+pub fn debug_print_card_counts_stats(result: &SimulationResult) {
+  for (index, section) in result.sections.iter().enumerate() {
+    if index > 0 {
+      println!();
+    }
+    println!("== {} ==", section.name);
+    print!("{}", section.text);
+  }
+}
@@ -1,98 +1,221 @@
-use std::collections::HashMap;
+use std::cell::RefCell;
+use std::rc::Rc;
 
 use crate::{
-  debug::{
-    debug_print_dice_roll, debug_print_game, debug_print_purchase_decision, debug_print_winner,
-  },
-  game::Game,
-  models::{player::OwnedCard, Card, CardColor, Landmark, Player},
+  config::GameConfig,
+  error::GameError,
+  events::{CoinSource, GameEvent, GameObserver},
+  game::{Game, GameOutcome},
+  models::{landmark::LandmarkType, player::OwnedCard, Card, CardColor, Landmark, Player},
   player_strategies::{
     player_strategy::{DiceRollDecision, PurchaseDecision},
-    PlayerStrategy,
+    PlayerStrategy, StrategySpec,
+  },
+  record::{DecisionLog, GameRecord, RecordingStrategy},
+  rules::{
+    card::activate_card,
+    landmark as LandmarkRules,
+    validation::{IllegalMovePolicy, Referee},
   },
-  rules::{card::activate_card, landmark as LandmarkRules},
 };
 
-const BUY_ONLY_TURNS: usize = 3;
-const MIN_PLAYERS: usize = 2;
-pub const MAX_PLAYERS: usize = 4;
-
 pub struct Engine {
   pub game: Game,
   pub player_strategies: Vec<Box<dyn PlayerStrategy>>,
+  pub referee: Referee,
+  // Spec of the strategy of every seat, written to game records
+  lineup: Vec<StrategySpec>,
+  observers: Vec<Box<dyn GameObserver>>,
+  decision_log: Option<DecisionLog>,
 }
 
 impl Engine {
-  pub fn new() -> Self {
+  pub fn new(config: GameConfig, rng_seed: Option<u64>) -> Self {
     Self {
-      game: Game::new(None),
+      game: Game::new(rng_seed, config),
       player_strategies: Vec::new(),
+      referee: Referee::new(IllegalMovePolicy::default()),
+      lineup: Vec::new(),
+      observers: Vec::new(),
+      decision_log: None,
+    }
+  }
+
+  /// Register an observer that receives all game events. Events are only recorded once there
+  /// is at least one observer.
+  pub fn add_observer(&mut self, observer: Box<dyn GameObserver>) {
+    self.observers.push(observer);
+    self.game.set_record_events(true);
+  }
+
+  /// Start recording the decisions of all strategies. Call after all players are added.
+  pub fn start_recording(&mut self) {
+    let log: DecisionLog = Rc::new(RefCell::new(Vec::new()));
+    let strategies = std::mem::take(&mut self.player_strategies);
+    for (seat, strategy) in strategies.into_iter().enumerate() {
+      let recording = RecordingStrategy::new(strategy, seat, log.clone());
+      self.player_strategies.push(Box::new(recording));
     }
+    self.decision_log = Some(log);
+  }
+
+  /// Record of the game so far, or None if recording was not started
+  pub fn get_record(&self) -> Option<GameRecord> {
+    let log = self.decision_log.as_ref()?;
+    Some(GameRecord {
+      seed: self.game.seed,
+      config: self.game.config.clone(),
+      illegal_move_policy: self.referee.policy,
+      lineup: self.lineup.clone(),
+      decisions: log.borrow().clone(),
+    })
+  }
+
+  /// Deliver all recorded events to the observers
+  fn dispatch_events(&mut self) {
+    for event in self.game.take_events() {
+      for observer in self.observers.iter_mut() {
+        observer.on_event(&event, &self.game);
+      }
+    }
+  }
+
+  /// Coins of every player before an effect, only taken when events are recorded
+  fn coins_snapshot(&self) -> Option<Vec<u16>> {
+    if self.game.is_recording_events() {
+      Some(self.game.coins_snapshot())
+    } else {
+      None
+    }
+  }
+
+  /// Emits a landmark event if the landmark effect changed anyone's coins (or always if forced)
+  fn emit_landmark_triggered(
+    &mut self,
+    landmark: Landmark,
+    snapshot: Option<Vec<u16>>,
+    force: bool,
+  ) {
+    if let Some(snapshot) = snapshot {
+      let coin_changes = self.game.coin_changes_since(&snapshot);
+      if force || coin_changes.iter().any(|change| *change != 0) {
+        self.game.emit(GameEvent::LandmarkTriggered {
+          landmark,
+          player_index: self.game.current_player,
+          coin_changes,
+        });
+      }
+    }
+  }
+
+  /// Set what happens when a strategy makes an illegal decision
+  pub fn set_illegal_move_policy(&mut self, policy: IllegalMovePolicy) {
+    self.referee.policy = policy;
   }
 
   /// Add a player strategy and a player to the game. The two vectors are the same length.
   pub fn add_player_strategy(&mut self, strategy: Box<dyn PlayerStrategy>) {
+    let spec = StrategySpec::new(strategy.name());
+    self.add_player_strategy_with_spec(strategy, spec);
+  }
+
+  /// Like `add_player_strategy`, for a strategy built from a spec. Records of the game name the
+  /// seat by the full spec, parameters included.
+  pub fn add_player_strategy_with_spec(
+    &mut self,
+    strategy: Box<dyn PlayerStrategy>,
+    spec: StrategySpec,
+  ) {
     self.player_strategies.push(strategy);
-    self.game.players.push(Player::new());
+    self.lineup.push(spec);
+    self
+      .game
+      .players
+      .push(Player::new(self.game.config.starting_coins));
   }
 
-  pub fn run(&mut self) {
-    if self.player_strategies.len() < MIN_PLAYERS || self.player_strategies.len() > MAX_PLAYERS {
-      panic!("Invalid number of players");
+  /// Plays the game until there is a winner or the game ends in a draw
+  pub fn run(&mut self) -> Result<GameOutcome, GameError> {
+    let player_count = self.player_strategies.len();
+    if player_count < self.game.config.min_players || player_count > self.game.config.max_players {
+      return Err(GameError::InvalidPlayerCount(player_count));
     }
 
-    debug_print_game(&self.game);
-    loop {
-      self.play_turn();
-      debug_print_game(&self.game);
+    let outcome = loop {
+      self.play_turn()?;
 
-      if self.game.winner().is_some() {
-        break;
+      if let Some(outcome) = self.game.outcome() {
+        break outcome;
       }
-    }
+    };
 
-    debug_print_winner(self.game.winner().unwrap());
+    self.game.emit(GameEvent::GameEnded { outcome });
+    self.dispatch_events();
+    Ok(outcome)
   }
 
-  pub fn play_turn(&mut self) {
-    if (self.game.get_round() < BUY_ONLY_TURNS) {
-      self.play_buy_only_turn();
+  pub fn play_turn(&mut self) -> Result<(), GameError> {
+    let player_index = self.game.current_player;
+    self.game.emit(GameEvent::TurnStarted {
+      player_index,
+      round: self.game.get_round(),
+    });
+    if self.game.get_round() < self.game.config.buy_only_turns {
+      self.play_buy_only_turn()?;
     } else {
-      self.play_normal_turn();
+      self.play_normal_turn()?;
     }
+    self.game.emit(GameEvent::TurnEnded { player_index });
+    self.dispatch_events();
     self.game.advance_turn();
+    Ok(())
   }
 
   /// First 3 turns are buy only turns
-  fn play_buy_only_turn(&mut self) {
+  fn play_buy_only_turn(&mut self) -> Result<(), GameError> {
     // Phase 1: Buy card
     // Buy phase for the first 3 turns is a little bit different. It does not trigger any effects.
     let decision = self.player_strategies[self.game.current_player].decide_purchase(&self.game);
-    debug_print_purchase_decision(decision);
+    let decision = self.referee.check_purchase(&mut self.game, decision)?;
+    self.game.emit(GameEvent::Purchase {
+      player_index: self.game.current_player,
+      decision,
+    });
 
     match decision {
       PurchaseDecision::BuyCard(card) => self.game.buy_card(card),
       PurchaseDecision::BuyLandmark(landmark) => self.game.buy_landmark(landmark),
-      PurchaseDecision::BuyNothing => {}
+      PurchaseDecision::BuyNothing => Ok(()),
     }
   }
 
   /// Normal turn has 3 phases: Roll dice, earn income, buy card or landmark
-  fn play_normal_turn(&mut self) {
+  fn play_normal_turn(&mut self) -> Result<(), GameError> {
     // Phase 1: Roll dice
-    let dice_roll_sum = self.roll_dice_phase();
+    let dice_roll_sum = self.roll_dice_phase()?;
+    if self.current_player_forfeited() {
+      return Ok(());
+    }
 
     // Phase 2: Earn income
-    self.earn_income_phase(dice_roll_sum);
+    self.earn_income_phase(dice_roll_sum)?;
+    if self.current_player_forfeited() {
+      return Ok(());
+    }
 
     // Phase 3: Buy card or landmark
-    self.buy_phase();
+    self.buy_phase()
+  }
+
+  /// Current player may forfeit mid-turn after an illegal decision, which ends their turn
+  fn current_player_forfeited(&self) -> bool {
+    self.game.get_current_player().forfeited
   }
 
   /// Phase 1: Roll dice
   /// In this phase, the player chooses to roll either one or two dice.
   /// The landmarks then trigger their effects based on the dice roll.
-  fn roll_dice_phase(&mut self) -> u8 {
+  fn roll_dice_phase(&mut self) -> Result<u8, GameError> {
     let current_player = self.game.current_player;
     let decision = self.player_strategies[current_player].decide_dice_roll(&self.game);
     let dice_roll = match decision {
@@ -100,111 +223,163 @@ impl Engine {
       DiceRollDecision::RollTwo => self.game.roll_two_dice(),
     };
 
-    debug_print_dice_roll(dice_roll);
+    self.game.emit(GameEvent::DiceRolled {
+      player_index: current_player,
+      dice: dice_roll,
+    });
 
     // Clone landmarks to avoid borrow checker issues
     let active_landmarks: Vec<Landmark> = self.game.get_active_landmarks().to_vec();
     for landmark in active_landmarks.iter() {
+      let snapshot = self.coins_snapshot();
       LandmarkRules::on_dice_roll(
         *landmark,
         &mut self.game,
         dice_roll,
         &mut *self.player_strategies[current_player],
-      );
+        &mut self.referee,
+      )?;
+      self.emit_landmark_triggered(*landmark, snapshot, false);
     }
 
-    dice_roll.0 + dice_roll.1
+    Ok(dice_roll.0 + dice_roll.1)
   }
 
   /// Phase 2: Earn income (activate cards)
   /// All cards are activated in the order of their color.
   /// Activation order: Red -> Blue and Green -> Purple -> Orange/Landmarks
   /// For red cards, pay coins in reverse order of players
-  pub fn earn_income_phase(&mut self, dice_roll_sum: u8) {
-    // Collect cards to activate (to avoid borrow conflicts). Vec<(card, card owner index)>
+  pub fn earn_income_phase(&mut self, dice_roll_sum: u8) -> Result<(), GameError> {
+    // Collect cards to activate (to avoid borrow conflicts).
+    // Vec<(card, card owner index, id of the owned card)>
     // Pre-allocate with capacity for ~4 players * ~15 cards = 60 max
-    let mut cards_to_activate: Vec<(Card, usize)> = Vec::with_capacity(60);
+    let mut cards_to_activate: Vec<(Card, usize, usize)> = Vec::with_capacity(60);
 
     // Collect red cards (all players except the current player)
     for player_index in self.game.other_players_reverse() {
-      for OwnedCard { card, .. } in self.game.players[player_index].cards.iter() {
+      if self.game.players[player_index].forfeited {
+        continue;
+      }
+      for OwnedCard { card, id, .. } in self.game.players[player_index].cards.iter() {
         if card.def().color == CardColor::Red && card.def().activation.contains(&dice_roll_sum) {
-          cards_to_activate.push((*card, player_index));
+          cards_to_activate.push((*card, player_index, *id));
         }
       }
     }
 
     // Collect blue cards (all players)
     for (player_index, player) in self.game.players.iter().enumerate() {
-      for OwnedCard { card, .. } in player.cards.iter() {
+      if player.forfeited {
+        continue;
+      }
+      for OwnedCard { card, id, .. } in player.cards.iter() {
         if card.def().color == CardColor::Blue && card.def().activation.contains(&dice_roll_sum) {
-          cards_to_activate.push((*card, player_index));
+          cards_to_activate.push((*card, player_index, *id));
         }
       }
     }
 
     // Collect green cards (current player)
-    for OwnedCard { card, .. } in self.game.players[self.game.current_player].cards.iter() {
+    for OwnedCard { card, id, .. } in self.game.players[self.game.current_player].cards.iter() {
       if card.def().color == CardColor::Green && card.def().activation.contains(&dice_roll_sum) {
-        cards_to_activate.push((*card, self.game.current_player));
+        cards_to_activate.push((*card, self.game.current_player, *id));
       }
     }
 
     // Collect purple cards (current player)
-    for OwnedCard { card, .. } in self.game.players[self.game.current_player].cards.iter() {
+    for OwnedCard { card, id, .. } in self.game.players[self.game.current_player].cards.iter() {
       if card.def().color == CardColor::Purple && card.def().activation.contains(&dice_roll_sum) {
-        cards_to_activate.push((*card, self.game.current_player));
+        cards_to_activate.push((*card, self.game.current_player, *id));
       }
     }
 
     // Activate collected cards
     let mut coins_received = false;
-    for (card, player_index) in cards_to_activate {
-      let coins_before = self.game.players[player_index].coins;
-      activate_card(
+    for (card, player_index, card_id) in cards_to_activate {
+      // A seat that forfeited during the phase collects nothing from its remaining cards
+      if self.game.players[player_index].forfeited {
+        continue;
+      }
+      let snapshot = self.coins_snapshot();
+      let income = activate_card(
         card,
         &mut self.game,
         player_index,
         &mut *self.player_strategies[player_index],
-      );
-      coins_received |= coins_before < self.game.players[player_index].coins;
+        &mut self.referee,
+      )?;
+      if income > 0 {
+        coins_received = true;
+        self.game.add_card_income(player_index, card_id, income);
+      }
+      if let Some(snapshot) = snapshot {
+        let coin_changes = self.game.coin_changes_since(&snapshot);
+        self.game.emit(GameEvent::CardActivated {
+          card,
+          owner_index: player_index,
+          coin_changes,
+        });
+      }
+      if self.current_player_forfeited() {
+        return Ok(());
+      }
     }
-    LandmarkRules::on_after_card_activation(&mut self.game, coins_received);
+    let snapshot = self.coins_snapshot();
+    if let Some(landmark) = LandmarkRules::on_after_card_activation(&mut self.game, coins_received)
+    {
+      self.emit_landmark_triggered(landmark, snapshot, false);
+    }
+    Ok(())
   }
 
-  // TODO ensure player can only own 1 purple card of each type
-
   /// Phase 3: Buy card or landmark
-  /// If the player has no coins, get 1 coin from the bank. The player then also chooses to
-  /// either buy a card or landmark, or do nothing.
+  /// If the player has no coins, get coins from the bank (1 by default). The player then also
+  /// chooses to either buy a card or landmark, or do nothing.
   /// After the player has made their decision, the landmarks trigger their effects based on whether
   /// the player built something this turn.
-  fn buy_phase(&mut self) {
-    // If the player has no coins, get 1 coin from the bank
+  fn buy_phase(&mut self) -> Result<(), GameError> {
+    // If the player has no coins, get coins from the bank
     if self.game.players[self.game.current_player].coins == 0 {
-      self.game.get_coins_from_bank(self.game.current_player, 1);
+      let coins_when_broke = self.game.config.coins_when_broke;
+      self.game.get_coins_from_bank(
+        self.game.current_player,
+        coins_when_broke,
+        CoinSource::BankPayout,
+      );
     }
 
     let mut built_something_this_turn = false;
 
     let decision = self.player_strategies[self.game.current_player].decide_purchase(&self.game);
-    debug_print_purchase_decision(decision);
+    let decision = self.referee.check_purchase(&mut self.game, decision)?;
+    self.game.emit(GameEvent::Purchase {
+      player_index: self.game.current_player,
+      decision,
+    });
 
     match decision {
       PurchaseDecision::BuyCard(card) => {
-        self.game.buy_card(card);
+        self.game.buy_card(card)?;
         built_something_this_turn = true;
       }
       PurchaseDecision::BuyLandmark(landmark) => {
-        self.game.buy_landmark(landmark);
+        self.game.buy_landmark(landmark)?;
         built_something_this_turn = true;
 
         // Activate built landmark
+        let snapshot = self.coins_snapshot();
         LandmarkRules::activate_landmark(landmark, &mut self.game);
+        if landmark.def().landmark_type == LandmarkType::Immediate {
+          self.emit_landmark_triggered(landmark, snapshot, true);
+        }
       }
       PurchaseDecision::BuyNothing => {}
     }
 
-    LandmarkRules::on_turn_end(&mut self.game, built_something_this_turn);
+    let snapshot = self.coins_snapshot();
+    if let Some(landmark) = LandmarkRules::on_turn_end(&mut self.game, built_something_this_turn) {
+      self.emit_landmark_triggered(landmark, snapshot, false);
+    }
+    Ok(())
   }
 }
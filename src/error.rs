@@ -0,0 +1,75 @@
+use std::fmt;
+
+use crate::models::{Card, Landmark};
+use crate::rules::validation::Violation;
+
+/// Errors returned by mutating methods on Game, Player and Engine when an illegal action is
+/// attempted. The game state is left unchanged when an error is returned.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GameError {
+  CannotAffordCard(Card),
+  CannotAffordLandmark(Landmark),
+  CardNotAvailable(Card),
+  LandmarkNotAvailable(Landmark),
+  PurpleCardAlreadyOwned(Card),
+  // (Player index, card)
+  CardNotOwned(usize, Card),
+  // (Player's card, opponents index, opponents card)
+  IllegalExchange(Card, usize, Card),
+  IllegalGive(Card),
+  MustGiveEstablishment,
+  InvalidPlayerCount(usize),
+  // A strategy decision was illegal under the Panic policy
+  IllegalDecision(Box<Violation>),
+}
+
+impl fmt::Display for GameError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      GameError::CannotAffordCard(card) => write!(f, "Cannot afford card {}", card.def().name),
+      GameError::CannotAffordLandmark(landmark) => {
+        write!(f, "Cannot afford landmark {}", landmark.def().name)
+      }
+      GameError::CardNotAvailable(card) => {
+        write!(f, "Card {} is not available for purchase", card.def().name)
+      }
+      GameError::LandmarkNotAvailable(landmark) => {
+        write!(
+          f,
+          "Landmark {} is not available for purchase",
+          landmark.def().name
+        )
+      }
+      GameError::PurpleCardAlreadyOwned(card) => {
+        write!(f, "Purple card {} is already owned", card.def().name)
+      }
+      GameError::CardNotOwned(player_index, card) => {
+        write!(
+          f,
+          "Player {} does not own card {}",
+          player_index,
+          card.def().name
+        )
+      }
+      GameError::IllegalExchange(card, opponent_index, opponent_card) => write!(
+        f,
+        "Illegal exchange of {} for {} from player {}",
+        card.def().name,
+        opponent_card.def().name,
+        opponent_index
+      ),
+      GameError::IllegalGive(card) => {
+        write!(
+          f,
+          "Illegal give of {} to the player on the right",
+          card.def().name
+        )
+      }
+      GameError::MustGiveEstablishment => write!(f, "An establishment must be given"),
+      GameError::InvalidPlayerCount(count) => write!(f, "Invalid number of players: {}", count),
+      GameError::IllegalDecision(violation) => write!(f, "Illegal decision. {}", violation),
+    }
+  }
+}
+
+impl std::error::Error for GameError {}
@@ -0,0 +1,90 @@
+use crate::game::{Game, GameOutcome};
+use crate::models::{Card, Landmark};
+use crate::player_strategies::player_strategy::PurchaseDecision;
+
+/// Something that happened in the game. Coin changes are given as a difference in coins for
+/// every player, indexed by player index.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GameEvent {
+  TurnStarted {
+    player_index: usize,
+    round: usize,
+  },
+  // Second die is 0 when only one die was rolled
+  DiceRolled {
+    player_index: usize,
+    dice: (u8, u8),
+  },
+  CardActivated {
+    card: Card,
+    owner_index: usize,
+    coin_changes: Vec<i32>,
+  },
+  LandmarkTriggered {
+    landmark: Landmark,
+    player_index: usize,
+    coin_changes: Vec<i32>,
+  },
+  // Coins taken by one player from another
+  CoinTransfer {
+    from_index: usize,
+    to_index: usize,
+    coins: u16,
+    source: CoinSource,
+  },
+  // Coins paid by the bank to a player, negative if the player paid the bank (Park)
+  BankTransfer {
+    player_index: usize,
+    coins: i32,
+    source: CoinSource,
+  },
+  Purchase {
+    player_index: usize,
+    decision: PurchaseDecision,
+  },
+  EstablishmentExchanged {
+    player_index: usize,
+    card: Card,
+    opponent_index: usize,
+    opponent_card: Card,
+  },
+  EstablishmentGiven {
+    player_index: usize,
+    to_index: usize,
+    card: Card,
+  },
+  ExtraTurn {
+    player_index: usize,
+  },
+  TurnEnded {
+    player_index: usize,
+  },
+  GameEnded {
+    outcome: GameOutcome,
+  },
+}
+
+/// Card or landmark whose effect moved coins, or the payout for a player who has no coins
+/// before buying
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CoinSource {
+  Card(Card),
+  Landmark(Landmark),
+  BankPayout,
+}
+
+impl CoinSource {
+  pub fn name(&self) -> &'static str {
+    match self {
+      CoinSource::Card(card) => card.def().name,
+      CoinSource::Landmark(landmark) => landmark.def().name,
+      CoinSource::BankPayout => "Bank payout",
+    }
+  }
+}
+
+/// Observers are registered on the engine and receive every event in the order it happened.
+/// Events are delivered at the end of each turn, along with the game state at that point.
+pub trait GameObserver {
+  fn on_event(&mut self, event: &GameEvent, game: &Game);
+}
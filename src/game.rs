@@ -1,18 +1,46 @@
 use std::collections::HashMap;
 
+use serde::{Deserialize, Serialize, Serializer};
+
+use crate::config::GameConfig;
+use crate::error::GameError;
+use crate::events::GameEvent;
 use crate::models::landmark::LandmarkType;
+use crate::models::player::DiceRoll;
 use crate::models::{Card, Landmark, Player};
 use crate::rng::Rng;
 use crate::rules::card::build_greater_than_6_deck;
 use crate::rules::card::build_less_than_7_deck;
 use crate::rules::landmark::build_landmark_deck;
 
-const LANDMARKS_TO_WIN: usize = 3;
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum DrawReason {
+  TurnLimit,
+  Stalemate,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GameOutcome {
+  Win(usize),
+  Draw(DrawReason),
+}
 
-#[derive(Clone)]
+impl GameOutcome {
+  pub fn winner(&self) -> Option<usize> {
+    match self {
+      GameOutcome::Win(index) => Some(*index),
+      GameOutcome::Draw(_) => None,
+    }
+  }
+}
+
+/// The full game state. Serializes to JSON including the RNG position, so a saved game continues
+/// exactly as the original would have.
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Game {
   pub rng: Rng,
   pub seed: u64,
+  pub config: GameConfig,
 
   pub current_player: usize,
   pub current_turn: usize,
@@ -25,7 +53,9 @@ pub struct Game {
   pub landmark_deck: Vec<Landmark>,
 
   // Face-up cards: HashMap<Card, count>
+  #[serde(serialize_with = "serialize_face_up")]
   pub less_than_7_face_up: HashMap<Card, u8>,
+  #[serde(serialize_with = "serialize_face_up")]
   pub greater_than_6_face_up: HashMap<Card, u8>,
   pub landmark_face_up: Vec<Landmark>,
 
@@ -34,17 +64,39 @@ pub struct Game {
 
   // Private state describing some game aspects
   take_another_turn: bool,
+  // Last turn in which anyone built a card or landmark, used for stalemate detection
+  last_build_turn: usize,
+  // Id of the next owned card
+  #[serde(default)]
+  next_card_id: usize,
+
+  // Events are only recorded when someone is observing the game. Observers are not part of the
+  // saved state, so neither are the events.
+  #[serde(skip)]
+  record_events: bool,
+  #[serde(skip)]
+  events: Vec<GameEvent>,
+}
+
+/// Face-up cards are saved in card order so the same game always saves to the same JSON
+fn serialize_face_up<S: Serializer>(
+  face_up: &HashMap<Card, u8>,
+  serializer: S,
+) -> Result<S::Ok, S::Error> {
+  let mut cards = face_up.iter().collect::<Vec<_>>();
+  cards.sort_by_key(|(card, _)| **card as usize);
+  serializer.collect_map(cards)
 }
 
 impl Game {
-  pub fn new(rng_seed: Option<u64>) -> Self {
+  pub fn new(rng_seed: Option<u64>, config: GameConfig) -> Self {
     let mut rng = match rng_seed {
       Some(seed) => Rng::new_with_seed(seed),
       None => Rng::new(),
     };
 
-    let mut less_than_7_deck = build_less_than_7_deck();
-    let mut greater_than_6_deck = build_greater_than_6_deck();
+    let mut less_than_7_deck = build_less_than_7_deck(&config.deck_composition);
+    let mut greater_than_6_deck = build_greater_than_6_deck(&config.deck_composition);
     let mut landmark_deck = build_landmark_deck();
 
     rng.shuffle(&mut less_than_7_deck);
@@ -55,6 +107,7 @@ impl Game {
     let mut game = Self {
       rng,
       seed,
+      config,
       current_player: 0,
       current_turn: 0,
       players: Vec::new(),
@@ -66,13 +119,24 @@ impl Game {
       landmark_face_up: Vec::new(),
       active_landmarks_cache: Vec::new(),
       take_another_turn: false,
+      last_build_turn: 0,
+      next_card_id: 0,
+      record_events: false,
+      events: Vec::new(),
     };
 
-    // Initialize face-up cards (5 for each deck)
+    // Initialize face-up cards
     game.refill_face_up_cards();
     game
   }
 
+  /// Id for a card that is bought or changes hands
+  pub(crate) fn new_card_id(&mut self) -> usize {
+    let id = self.next_card_id;
+    self.next_card_id += 1;
+    id
+  }
+
   pub fn get_round(&self) -> usize {
     self.current_turn / self.players.len()
   }
@@ -82,8 +146,24 @@ impl Game {
   }
 
   pub fn winner(&self) -> Option<usize> {
-    for (index, player) in self.players.iter().enumerate() {
-      if player.landmarks.len() == LANDMARKS_TO_WIN
+    // Last player standing wins if everyone else forfeited
+    let mut remaining = self
+      .players
+      .iter()
+      .enumerate()
+      .filter(|(_, p)| !p.forfeited);
+    if let (Some((index, _)), None) = (remaining.next(), remaining.next()) {
+      if self.players.len() > 1 {
+        return Some(index);
+      }
+    }
+    for (index, player) in self
+      .players
+      .iter()
+      .enumerate()
+      .filter(|(_, p)| !p.forfeited)
+    {
+      if player.landmarks.len() == self.config.landmarks_to_win
         || player
           .landmarks
           .iter()
@@ -95,12 +175,58 @@ impl Game {
     None
   }
 
+  /// Game ends either with a winner, or in a draw when the round limit is reached or nobody has
+  /// built anything for too long
+  pub fn outcome(&self) -> Option<GameOutcome> {
+    if let Some(winner) = self.winner() {
+      return Some(GameOutcome::Win(winner));
+    }
+    let max_rounds = self.config.max_rounds;
+    if max_rounds > 0 && self.get_round() >= max_rounds {
+      return Some(GameOutcome::Draw(DrawReason::TurnLimit));
+    }
+    let stalemate_turns = self.config.stalemate_rounds * self.players.len();
+    if stalemate_turns > 0 && self.current_turn - self.last_build_turn >= stalemate_turns {
+      return Some(GameOutcome::Draw(DrawReason::Stalemate));
+    }
+    None
+  }
+
+  /// Place of every player, 1 being the best. The winner comes first, the others are ranked by
+  /// built landmarks and then by coins, forfeited players come last. Equal players share a place.
+  pub fn placements(&self) -> Vec<usize> {
+    let winner = self.winner();
+    let keys: Vec<(bool, bool, usize, u16)> = self
+      .players
+      .iter()
+      .enumerate()
+      .map(|(index, player)| {
+        (
+          winner == Some(index),
+          !player.forfeited,
+          player.landmarks.len(),
+          player.coins,
+        )
+      })
+      .collect();
+    keys
+      .iter()
+      .map(|key| 1 + keys.iter().filter(|other| *other > key).count())
+      .collect()
+  }
+
   pub fn current_player_can_afford_card(&self, card: &Card) -> bool {
     self.players[self.current_player].can_afford_card(card)
   }
 
+  /// Current player can buy the card if they can afford it and are allowed to own another copy
+  pub fn current_player_can_buy_card(&self, card: &Card) -> bool {
+    let player = &self.players[self.current_player];
+    player.can_afford_card(card) && player.can_own_card(card)
+  }
+
   pub fn current_player_can_afford_landmark(&self, landmark: &Landmark) -> bool {
-    self.players[self.current_player].can_afford_landmark(landmark)
+    self.players[self.current_player].can_afford_landmark(landmark, &self.active_landmarks_cache)
   }
 
   /// Get all active landmarks (infinite landmarks apply to all players once built)
@@ -109,43 +235,103 @@ impl Game {
     &self.active_landmarks_cache
   }
 
+  /// Forfeited players are skipped, but their turns still count towards the round
   pub fn advance_turn(&mut self) {
-    if self.take_another_turn {
+    if self.take_another_turn && !self.players[self.current_player].forfeited {
       self.take_another_turn = false;
       return;
     }
-    self.current_player = (self.current_player + 1) % self.players.len();
-    self.current_turn += 1;
+    self.take_another_turn = false;
+    loop {
+      self.current_player = (self.current_player + 1) % self.players.len();
+      self.current_turn += 1;
+      if !self.players[self.current_player].forfeited {
+        break;
+      }
+    }
+  }
+
+  /// Removes the player from the game. Their cards stay on the table but are never activated.
+  pub fn forfeit_player(&mut self, player_index: usize) {
+    self.players[player_index].forfeited = true;
   }
 
   /// Reverts the current turn so that the current player can take another turn
   pub fn take_another_turn_after_this_one(&mut self) {
     self.take_another_turn = true;
+    self.emit(GameEvent::ExtraTurn {
+      player_index: self.current_player,
+    });
+  }
+
+  pub fn set_record_events(&mut self, record_events: bool) {
+    self.record_events = record_events;
+  }
+
+  pub fn is_recording_events(&self) -> bool {
+    self.record_events
+  }
+
+  /// Record an event. Does nothing if events are not being recorded.
+  pub fn emit(&mut self, event: GameEvent) {
+    if self.record_events {
+      self.events.push(event);
+    }
+  }
+
+  /// Take all events recorded since the last call
+  pub fn take_events(&mut self) -> Vec<GameEvent> {
+    std::mem::take(&mut self.events)
+  }
+
+  /// Coins of every player, used to compute coin changes for events
+  pub fn coins_snapshot(&self) -> Vec<u16> {
+    self.players.iter().map(|player| player.coins).collect()
+  }
+
+  /// Difference in coins for every player compared to the snapshot
+  pub fn coin_changes_since(&self, snapshot: &[u16]) -> Vec<i32> {
+    self
+      .players
+      .iter()
+      .zip(snapshot.iter())
+      .map(|(player, before)| player.coins as i32 - *before as i32)
+      .collect()
   }
 
   pub fn roll_one_die(&mut self) -> u8 {
-    let round = self.get_round() as u8;
-    self.players[self.current_player]
-      .dice_rolls
-      .push((1, round));
+    self.add_dice_roll(1);
     self.rng.roll_die()
   }
 
   pub fn roll_two_dice(&mut self) -> (u8, u8) {
-    let round = self.get_round() as u8;
-    self.players[self.current_player]
-      .dice_rolls
-      .push((2, round));
+    self.add_dice_roll(2);
     self.rng.roll_two_dice()
   }
 
+  fn add_dice_roll(&mut self, dice: u8) {
+    let roll = DiceRoll {
+      dice,
+      round: self.get_round(),
+      next_card_id: self.next_card_id,
+    };
+    self.players[self.current_player].dice_rolls.push(roll);
+  }
+
   /// 1. Add card to player's cards
   /// 2. Subtract card cost from player's coins
   /// 3. Remove card from face-up cards
   /// 4. Refill face-up cards
-  pub fn buy_card(&mut self, card: Card) {
-    let bought_round = self.get_round() as u8;
-    self.players[self.current_player].buy_card(card, bought_round);
+  pub fn buy_card(&mut self, card: Card) -> Result<(), GameError> {
+    if !self.less_than_7_face_up.contains_key(&card)
+      && !self.greater_than_6_face_up.contains_key(&card)
+    {
+      return Err(GameError::CardNotAvailable(card));
+    }
+    let bought_round = self.get_round();
+    let id = self.new_card_id();
+    self.players[self.current_player].buy_card(card, bought_round, id)?;
+    self.last_build_turn = self.current_turn;
 
     if let Some(count) = self.less_than_7_face_up.get_mut(&card) {
       *count -= 1;
@@ -160,6 +346,7 @@ impl Game {
       }
     }
     self.refill_face_up_cards();
+    Ok(())
   }
 
   /// 1. Add landmark to player's landmarks
@@ -167,36 +354,86 @@ impl Game {
   /// 3. Remove landmark from face-up landmarks
   /// 4. Refill face-up landmarks
   /// 5. Add to active landmarks cache if infinite
-  pub fn buy_landmark(&mut self, landmark: Landmark) {
-    let bought_round = self.get_round() as u8;
-    self.players[self.current_player].buy_landmark(landmark, bought_round);
-    if let Some(pos) = self.landmark_face_up.iter().position(|l| *l == landmark) {
-      self.landmark_face_up.remove(pos);
-    }
+  pub fn buy_landmark(&mut self, landmark: Landmark) -> Result<(), GameError> {
+    let pos = self
+      .landmark_face_up
+      .iter()
+      .position(|l| *l == landmark)
+      .ok_or(GameError::LandmarkNotAvailable(landmark))?;
+    let bought_round = self.get_round();
+    self.players[self.current_player].buy_landmark(
+      landmark,
+      bought_round,
+      &self.active_landmarks_cache,
+    )?;
+    self.last_build_turn = self.current_turn;
+    self.landmark_face_up.remove(pos);
     self.refill_face_up_cards();
 
     // Add to active landmarks cache if infinite
     if landmark.def().landmark_type == LandmarkType::Infinite {
       self.active_landmarks_cache.push(landmark);
     }
+    Ok(())
   }
 
-  /// Refill all face-up card areas to maintain 5 unique cards each
+  /// Refill all face-up card areas to maintain the configured number of unique cards each
   fn refill_face_up_cards(&mut self) {
-    while self.less_than_7_face_up.len() < 5 && !self.less_than_7_deck.is_empty() {
+    let face_up_cards = self.config.face_up_cards;
+    while self.less_than_7_face_up.len() < face_up_cards && !self.less_than_7_deck.is_empty() {
       let card = self.less_than_7_deck.pop().unwrap();
       *self.less_than_7_face_up.entry(card).or_insert(0) += 1;
     }
-    while self.greater_than_6_face_up.len() < 5 && !self.greater_than_6_deck.is_empty() {
+    while self.greater_than_6_face_up.len() < face_up_cards && !self.greater_than_6_deck.is_empty()
+    {
       let card = self.greater_than_6_deck.pop().unwrap();
       *self.greater_than_6_face_up.entry(card).or_insert(0) += 1;
     }
-    while self.landmark_face_up.len() < 5 && !self.landmark_deck.is_empty() {
+    while self.landmark_face_up.len() < self.config.face_up_landmarks
+      && !self.landmark_deck.is_empty()
+    {
       let landmark = self.landmark_deck.pop().unwrap();
       self.landmark_face_up.push(landmark);
     }
   }
 
+  /// Index of the player to the right of the current player
+  pub fn player_to_the_right(&self) -> usize {
+    (self.players.len() + self.current_player - 1) % self.players.len()
+  }
+
+  /// Exchange is legal if both players own the cards and neither ends up with 2 copies of the
+  /// same purple card
+  pub fn can_exchange_establishment(
+    &self,
+    card: Card,
+    opponent_index: usize,
+    opponent_card: Card,
+  ) -> bool {
+    let player = &self.players[self.current_player];
+    let opponent = &self.players[opponent_index];
+    if opponent_index == self.current_player
+      || !player.cards.iter().any(|owned| owned.card == card)
+      || !opponent
+        .cards
+        .iter()
+        .any(|owned| owned.card == opponent_card)
+    {
+      return false;
+    }
+    card == opponent_card || (player.can_own_card(&opponent_card) && opponent.can_own_card(&card))
+  }
+
+  /// Giving is legal if the current player owns the card and the player to the right is allowed
+  /// to own it
+  pub fn can_give_establishment_to_right(&self, card: Card) -> bool {
+    self.players[self.current_player]
+      .cards
+      .iter()
+      .any(|owned| owned.card == card)
+      && self.players[self.player_to_the_right()].can_own_card(&card)
+  }
+
   /// Iterate over other players in reverse order
   pub fn other_players_reverse(&self) -> impl Iterator<Item = usize> {
     let player_count = self.players.len();
@@ -0,0 +1,28 @@
+//! Machi Koro simulator library. Play single games with the `Engine`, write your own
+//! `PlayerStrategy`, or run many games in parallel with the `Simulator`.
+
+pub mod config;
+pub mod debug;
+pub mod engine;
+pub mod error;
+pub mod events;
+pub mod game;
+pub mod math;
+pub mod models;
+pub mod optimization;
+pub mod player_strategies;
+pub mod record;
+pub mod rng;
+pub mod rules;
+pub mod simulation;
+pub mod statistics;
+
+pub use config::GameConfig;
+pub use engine::Engine;
+pub use error::GameError;
+pub use events::{GameEvent, GameObserver};
+pub use game::{DrawReason, Game, GameOutcome};
+pub use models::{Card, Landmark, Player};
+pub use player_strategies::PlayerStrategy;
+pub use record::{GameRecord, RecordError};
+pub use simulation::{SimulationResult, Simulator};
@@ -1,49 +1,521 @@
-#![allow(warnings)] // Disable all warnings
-
-mod debug;
-mod engine;
-mod game;
-mod math;
-mod models;
-mod optimization;
-mod player_strategies;
-mod rng;
-mod rules;
-mod simulation;
-
-use std::collections::HashMap;
-use std::sync::Mutex;
-
-use rayon::prelude::*;
+use clap::{Args, Parser, Subcommand, ValueEnum};
 use strum::IntoEnumIterator;
 
-use crate::{
-  debug::debug_print_card_counts_stats, engine::Engine, models::Card,
-  player_strategies::RandomStrategy, simulation::Simulator,
+use machi_koro_sim::{
+  debug::{debug_print_card_counts_stats, print_game, DebugPrinter},
+  player_strategies::{StrategyRegistry, StrategySpec},
+  record::replay,
+  rng::Rng,
+  rules::validation::IllegalMovePolicy,
+  simulation::{
+    metrics::CoinFlowMetric, save_game_rows, SeatingMode, SequentialTest, StoppingCriterion,
+  },
+  Card, Engine, GameConfig, GameOutcome, GameRecord, Landmark, Simulator,
 };
 
+/// Machi Koro game simulator. Files given to --output, --games and --coin-flow-games are written as
+/// CSV if their name ends in .csv and as JSON otherwise.
+#[derive(Parser)]
+#[command(name = "machi-koro-sim")]
+struct Cli {
+  #[command(subcommand)]
+  command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+  /// Run many games in parallel and print card and seat statistics
+  Simulate(SimulateArgs),
+  /// Play one game and print every turn
+  Play(PlayArgs),
+  /// Play every combination of the strategies against each other
+  Tournament(TournamentArgs),
+  /// Replay a recorded game and print every turn
+  Replay {
+    /// Record file written by `play --record`
+    file: String,
+  },
+  /// List all cards and landmarks
+  Cards,
+}
+
+#[derive(Args)]
+struct GameArgs {
+  /// Master seed, random if not given
+  #[arg(long)]
+  seed: Option<u64>,
+  /// What happens when a strategy makes an illegal decision (Panic, Substitute, Forfeit). Panic
+  /// ends the game with an error, a simulation then skips the game.
+  #[arg(long, default_value = "Substitute")]
+  policy: IllegalMovePolicy,
+}
+
+#[derive(Args)]
+struct LineupArgs {
+  /// Strategy spec of every seat, e.g. `random:seed=7 greedy:priority=ShoppingDistrict,Vineyard`.
+  /// Strategies: random, landmark, greedy, optimized.
+  #[arg(
+    long,
+    num_args = 1..,
+    default_values = ["random", "landmark", "random", "greedy"]
+  )]
+  lineup: Vec<StrategySpec>,
+  /// Number of players. The lineup is repeated or cut to this length.
+  #[arg(long)]
+  players: Option<usize>,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum OutputFormat {
+  Text,
+  Json,
+  Csv,
+}
+
+#[derive(Args)]
+struct SimulateArgs {
+  /// Number of games to simulate
+  #[arg(long, short = 'n', default_value_t = 1000)]
+  count: usize,
+  #[command(flatten)]
+  lineup: LineupArgs,
+  #[command(flatten)]
+  game: GameArgs,
+  /// How the lineup is seated in each game (Fixed, Rotate, Permute)
+  #[arg(long, default_value = "Fixed")]
+  seating: SeatingMode,
+  /// Format of the result printed to the console. CSV has one statistic per row.
+  #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+  format: OutputFormat,
+  /// Also save the result to this file
+  #[arg(long)]
+  output: Option<String>,
+  /// Save a row for every seat of every game to this file
+  #[arg(long, conflicts_with_all = ["until_win_rate", "until_card"])]
+  games: Option<String>,
+  /// Also report the coins moved between seats and strategies by every card and landmark. Slows
+  /// down the simulation, as every event of the games is recorded.
+  #[arg(long)]
+  coin_flow: bool,
+  /// Save the coins moved by every source between the seats of every game to this file. Implies
+  /// `--coin-flow`.
+  #[arg(long, conflicts_with_all = ["until_win_rate", "until_card"])]
+  coin_flow_games: Option<String>,
+  /// Play until the win rates of these two strategies of the lineup differ significantly,
+  /// `--count` is then the maximum number of games
+  #[arg(long, num_args = 2, value_names = ["A", "B"], conflicts_with = "until_card")]
+  until_win_rate: Option<Vec<StrategySpec>>,
+  /// Play until the card is significantly more or less often owned by the winner than by the
+  /// other players, `--count` is then the maximum number of games
+  #[arg(long)]
+  until_card: Option<Card>,
+  /// Significance level of --until-win-rate and --until-card
+  #[arg(long, default_value_t = 0.05)]
+  alpha: f64,
+  /// Games played between two looks of --until-win-rate and --until-card
+  #[arg(long, default_value_t = 500)]
+  batch: usize,
+}
+
+#[derive(Args)]
+struct PlayArgs {
+  #[command(flatten)]
+  lineup: LineupArgs,
+  #[command(flatten)]
+  game: GameArgs,
+  /// Master seed of a `simulate` run, to play one of its games again instead of a game seeded
+  /// with `--seed`
+  #[arg(long, requires = "game_index", conflicts_with = "seed")]
+  master_seed: Option<u64>,
+  /// Index of the game of the `--master-seed` run to play, counted from 0
+  #[arg(long = "game", requires = "master_seed", conflicts_with = "seed")]
+  game_index: Option<usize>,
+  /// How the lineup was seated in the `--master-seed` run (Fixed, Rotate, Permute)
+  #[arg(long, default_value = "Fixed", requires = "master_seed")]
+  seating: SeatingMode,
+  /// Save a record of the game to this file
+  #[arg(long)]
+  record: Option<String>,
+}
+
+#[derive(Args)]
+struct TournamentArgs {
+  /// Strategy specs taking part
+  #[arg(
+    long,
+    num_args = 1..,
+    default_values = ["random", "landmark", "greedy", "optimized"]
+  )]
+  strategies: Vec<StrategySpec>,
+  /// Table sizes to play, every combination of the strategies is played at each size
+  #[arg(long, num_args = 1.., default_values_t = [2, 3, 4])]
+  players: Vec<usize>,
+  /// Number of games played by every lineup
+  #[arg(long, short = 'n', default_value_t = 100)]
+  count: usize,
+  #[command(flatten)]
+  game: GameArgs,
+  /// How every lineup is seated in its games (Fixed, Rotate, Permute)
+  #[arg(long, default_value = "Permute")]
+  seating: SeatingMode,
+  /// Save the leaderboard to this file
+  #[arg(long)]
+  output: Option<String>,
+}
+
 fn main() {
-  let args: Vec<String> = std::env::args().collect();
-  let sim_count = if args.len() > 1 {
-    args[1].parse::<usize>().unwrap_or_else(|_| {
-      eprintln!("Invalid simulation count. Using default: 1");
-      1
-    })
-  } else {
-    1
+  let cli = Cli::parse();
+  let result = match cli.command {
+    Command::Simulate(args) => simulate(args),
+    Command::Play(args) => play(args),
+    Command::Tournament(args) => tournament(args),
+    Command::Replay { file } => replay_file(&file),
+    Command::Cards => {
+      print_cards();
+      Ok(())
+    }
   };
+  if let Err(message) = result {
+    eprintln!("Error: {}", message);
+    std::process::exit(1);
+  }
+}
+
+/// Validates the number of players against the default rules
+fn check_player_count(players: usize) -> Result<(), String> {
+  let config = GameConfig::default();
+  if players < config.min_players || players > config.max_players {
+    return Err(format!(
+      "Number of players must be between {} and {}",
+      config.min_players, config.max_players
+    ));
+  }
+  Ok(())
+}
+
+impl LineupArgs {
+  fn lineup(&self) -> Result<Vec<StrategySpec>, String> {
+    let players = self.players.unwrap_or(self.lineup.len());
+    check_player_count(players)?;
+    Ok(self.lineup.iter().cycle().take(players).cloned().collect())
+  }
+}
+
+impl GameArgs {
+  fn simulator(&self) -> Simulator {
+    let mut simulator = Simulator::new();
+    if let Some(seed) = self.seed {
+      simulator.set_master_seed(seed);
+    }
+    simulator.set_illegal_move_policy(self.policy);
+    simulator
+  }
+}
 
-  let num_players = 4;
+fn simulate(args: SimulateArgs) -> Result<(), String> {
+  let mut simulator = args.game.simulator();
+  simulator.set_seating(args.seating);
+  simulator
+    .set_lineup(args.lineup.lineup()?)
+    .map_err(|error| error.to_string())?;
+  if args.coin_flow || args.coin_flow_games.is_some() {
+    simulator.add_metric(CoinFlowMetric::new(
+      simulator.get_lineup().len(),
+      simulator.get_distinct_strategies(),
+    ));
+  }
 
-  println!("Running {} simulations in parallel...", sim_count);
+  let criterion = match (args.until_win_rate, args.until_card) {
+    (Some(strategies), _) => Some(StoppingCriterion::WinRate(
+      strategies[0].clone(),
+      strategies[1].clone(),
+    )),
+    (None, Some(card)) => Some(StoppingCriterion::CardPresence(card)),
+    (None, None) => None,
+  };
+  if let Some(criterion) = criterion {
+    let test = SequentialTest {
+      criterion,
+      significance: args.alpha,
+      max_games: args.count,
+      batch_size: args.batch,
+    };
+    return simulate_sequential(&simulator, &test, args.format, args.output);
+  }
 
   let start_time = std::time::Instant::now();
+  let result = if args.games.is_some() || args.coin_flow_games.is_some() {
+    let (result, rows) = simulator.run_with_game_rows(args.count);
+    if let Some(path) = &args.games {
+      save_game_rows(path, &rows.seats).map_err(|error| error.to_string())?;
+    }
+    if let Some(path) = &args.coin_flow_games {
+      save_game_rows(path, &rows.coin_flows).map_err(|error| error.to_string())?;
+    }
+    result
+  } else {
+    simulator.run(args.count)
+  };
+  let elapsed = start_time.elapsed();
 
-  let simulation = Simulator::new();
-  let result = simulation.run(sim_count);
+  match args.format {
+    OutputFormat::Text => {
+      println!(
+        "Simulated {} games in {:.2?} (seed {})",
+        args.count,
+        elapsed,
+        simulator.get_master_seed()
+      );
+      debug_print_card_counts_stats(&result);
+    }
+    OutputFormat::Json => {
+      println!("{}", result.to_json().map_err(|error| error.to_string())?);
+    }
+    OutputFormat::Csv => {
+      result
+        .write_csv(std::io::stdout())
+        .map_err(|error| error.to_string())?;
+    }
+  }
+  if let Some(path) = args.output {
+    result.save(&path).map_err(|error| error.to_string())?;
+  }
+  Ok(())
+}
 
+fn simulate_sequential(
+  simulator: &Simulator,
+  test: &SequentialTest,
+  format: OutputFormat,
+  output: Option<String>,
+) -> Result<(), String> {
+  let start_time = std::time::Instant::now();
+  let sequential = simulator
+    .run_sequential(test)
+    .map_err(|error| error.to_string())?;
   let elapsed = start_time.elapsed();
-  println!("Simulations completed in {:.2?}", elapsed);
 
-  debug_print_card_counts_stats(result);
+  match format {
+    OutputFormat::Text => {
+      let compared = match &test.criterion {
+        StoppingCriterion::WinRate(a, b) => format!("Win rate {} - {}", a, b),
+        StoppingCriterion::CardPresence(card) => {
+          format!("{} presence winner - others", card.def().name)
+        }
+      };
+      let verdict = if sequential.significant {
+        "significant"
+      } else {
+        "not significant"
+      };
+      println!(
+        "Simulated {} games in {:.2?} (seed {})",
+        sequential.game_count,
+        elapsed,
+        simulator.get_master_seed()
+      );
+      println!(
+        "{}: {:+.4}, p = {:.4}, {} at {} after {} of at most {} games",
+        compared,
+        sequential.difference,
+        sequential.p_value,
+        verdict,
+        test.significance,
+        sequential.game_count,
+        test.max_games
+      );
+      println!();
+      debug_print_card_counts_stats(&sequential.result);
+    }
+    OutputFormat::Json => {
+      println!(
+        "{}",
+        sequential.to_json().map_err(|error| error.to_string())?
+      );
+    }
+    OutputFormat::Csv => {
+      sequential
+        .write_csv(std::io::stdout())
+        .map_err(|error| error.to_string())?;
+    }
+  }
+  if let Some(path) = output {
+    sequential.save(&path).map_err(|error| error.to_string())?;
+  }
+  Ok(())
+}
+
+fn play(args: PlayArgs) -> Result<(), String> {
+  let lineup = args.lineup.lineup()?;
+  let mut engine = match (args.master_seed, args.game_index) {
+    // The same game as in a simulation run, with the seeds and seating the run derives for it
+    (Some(master_seed), Some(game_index)) => {
+      let mut simulator = args.game.simulator();
+      simulator.set_master_seed(master_seed);
+      simulator.set_seating(args.seating);
+      simulator
+        .set_lineup(lineup)
+        .map_err(|error| error.to_string())?;
+      let engine = simulator
+        .build_engine(game_index)
+        .map_err(|error| error.to_string())?;
+      println!("Game {} of the run with seed {}", game_index, master_seed);
+      engine
+    }
+    _ => {
+      let seed = args.game.seed.unwrap_or_else(|| Rng::new().get_seed());
+      let mut engine = Engine::new(GameConfig::default(), Some(seed));
+      engine.set_illegal_move_policy(args.game.policy);
+      let registry = StrategyRegistry::with_builtin_strategies();
+      for (seat, spec) in lineup.iter().enumerate() {
+        let seat_seed = Rng::derive_seed(seed, seat as u64);
+        let strategy = registry
+          .build(spec, seat_seed)
+          .map_err(|error| error.to_string())?;
+        engine.add_player_strategy_with_spec(strategy, spec.clone());
+      }
+      engine
+    }
+  };
+  engine.add_observer(Box::new(DebugPrinter::new()));
+  if args.record.is_some() {
+    engine.start_recording();
+  }
+
+  println!("Playing one game (seed {})", engine.game.seed);
+  engine.run().map_err(|error| error.to_string())?;
+
+  if let (Some(path), Some(record)) = (args.record, engine.get_record()) {
+    record.save(&path).map_err(|error| error.to_string())?;
+    println!("Saved record to {}", path);
+  }
+  Ok(())
+}
+
+fn tournament(args: TournamentArgs) -> Result<(), String> {
+  for players in args.players.iter() {
+    check_player_count(*players)?;
+  }
+  let mut strategies: Vec<StrategySpec> = Vec::new();
+  for strategy in args.strategies {
+    if !strategies.contains(&strategy) {
+      strategies.push(strategy);
+    }
+  }
+
+  let mut simulator = args.game.simulator();
+  simulator.set_seating(args.seating);
+  let result = simulator
+    .run_tournament(&strategies, &args.players, args.count)
+    .map_err(|error| error.to_string())?;
+  if result.lineup_count == 0 {
+    return Err("Not enough different strategies for any of the table sizes".to_string());
+  }
+
+  println!(
+    "{} lineups x {} games (seed {})",
+    result.lineup_count,
+    args.count,
+    simulator.get_master_seed()
+  );
+  let width = strategies
+    .iter()
+    .map(|strategy| strategy.to_string().len())
+    .max()
+    .unwrap_or(0)
+    .max(16);
+  println!(
+    "{:>4} {:<width$} {:>8} {:>14} {:>8} {:>8} {:>8} {:>8} {:>10}",
+    "Rank", "Strategy", "Rating", "Skill", "Games", "Wins", "Draws", "Win %", "Avg place"
+  );
+  for (rank, standing) in result.standings.iter().enumerate() {
+    println!(
+      "{:>4} {:<width$} {:>8.2} {:>14} {:>8} {:>8} {:>8} {:>8.2} {:>10.2}",
+      rank + 1,
+      standing.strategy.to_string(),
+      standing.rating.conservative(),
+      format!("{:.2} ± {:.2}", standing.rating.mu, standing.rating.sigma),
+      standing.games,
+      standing.wins,
+      standing.draws,
+      standing.win_rate() * 100.0,
+      standing.average_placement()
+    );
+  }
+
+  if let Some(path) = args.output {
+    result.save(&path).map_err(|error| error.to_string())?;
+    println!("Saved leaderboard to {}", path);
+  }
+  Ok(())
+}
+
+fn replay_file(path: &str) -> Result<(), String> {
+  let record = GameRecord::load(path).map_err(|error| error.to_string())?;
+  let games = replay(&record).map_err(|error| error.to_string())?;
+
+  println!(
+    "Seed {} | players: {}",
+    record.seed,
+    record
+      .lineup
+      .iter()
+      .map(|spec| spec.to_string())
+      .collect::<Vec<_>>()
+      .join(", ")
+  );
+  for game in games.iter().skip(1) {
+    // States are taken after each turn, when the next player is already up
+    println!("=== After turn {} ===", game.current_turn);
+    print_game(game);
+  }
+  match games.last().and_then(|game| game.outcome()) {
+    Some(GameOutcome::Win(winner)) => println!("Player {} wins!", winner),
+    Some(GameOutcome::Draw(reason)) => println!("Draw ({:?})", reason),
+    None => {}
+  }
+  Ok(())
+}
+
+fn print_cards() {
+  println!(
+    "{:<20} {:<8} {:<10} {:>5} {:<10} Effect",
+    "Card", "Color", "Category", "Cost", "Roll"
+  );
+  for card in Card::iter() {
+    let def = card.def();
+    let activation = def
+      .activation
+      .iter()
+      .map(|roll| roll.to_string())
+      .collect::<Vec<_>>()
+      .join(",");
+    println!(
+      "{:<20} {:<8} {:<10} {:>5} {:<10} {}",
+      def.name,
+      format!("{:?}", def.color),
+      format!("{:?}", def.category),
+      def.cost,
+      activation,
+      def.effect
+    );
+  }
+
+  println!();
+  println!("{:<20} {:<10} Cost", "Landmark", "Type");
+  for landmark in Landmark::iter() {
+    let def = landmark.def();
+    let cost = def
+      .cost
+      .iter()
+      .map(|cost| cost.to_string())
+      .collect::<Vec<_>>()
+      .join("/");
+    println!(
+      "{:<20} {:<10} {}",
+      def.name,
+      format!("{:?}", def.landmark_type),
+      cost
+    );
+  }
 }
@@ -1,6 +1,7 @@
 use std::fmt;
 
-use strum::EnumIter;
+use serde::{Deserialize, Serialize};
+use strum::{EnumIter, EnumString, IntoStaticStr};
 
 #[derive(Clone, Debug, Hash, PartialEq, Eq)]
 pub enum CardColor {
@@ -93,7 +94,21 @@ pub struct CardDef {
   pub effect: CardEffect,
 }
 
-#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, EnumIter)]
+#[derive(
+  Clone,
+  Copy,
+  Debug,
+  Hash,
+  PartialEq,
+  Eq,
+  PartialOrd,
+  Ord,
+  EnumIter,
+  EnumString,
+  IntoStaticStr,
+  Serialize,
+  Deserialize,
+)]
 pub enum Card {
   SushiBar,
   WheatField,
@@ -1,6 +1,7 @@
 use std::fmt;
 
-use strum::EnumIter;
+use serde::{Deserialize, Serialize};
+use strum::{EnumIter, EnumString, IntoStaticStr};
 
 #[derive(Clone, Debug, Hash, PartialEq, Eq)]
 pub enum LandmarkType {
@@ -24,7 +25,21 @@ pub struct LandmarkDef {
   pub landmark_type: LandmarkType,
 }
 
-#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, EnumIter)]
+#[derive(
+  Clone,
+  Copy,
+  Debug,
+  Hash,
+  PartialEq,
+  Eq,
+  PartialOrd,
+  Ord,
+  EnumIter,
+  EnumString,
+  IntoStaticStr,
+  Serialize,
+  Deserialize,
+)]
 pub enum Landmark {
   Airport,
   AmusementPark,
@@ -1,33 +1,90 @@
-use super::card::Card;
+use serde::{Deserialize, Serialize};
+
+use super::card::{Card, CardColor};
 use super::landmark::Landmark;
+use crate::error::GameError;
+use crate::rules::landmark::get_landmark_cost;
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct OwnedCard {
   pub card: Card,
-  pub bought_round: u8,
+  // Unique within the game, a card that changes hands gets a new id
+  #[serde(default)]
+  pub id: usize,
+  pub bought_round: usize,
+  // Coins the card earned its owner, from the bank or taken from other players
+  #[serde(default)]
+  pub income: u16,
+  // Round in which the income first covered the cost of the card
+  #[serde(default)]
+  pub paid_back_round: Option<usize>,
+  // Id the card got when it changed hands, None while it is still owned
+  #[serde(default)]
+  pub retired_id: Option<usize>,
 }
 
-#[derive(Clone)]
+impl OwnedCard {
+  pub fn new(card: Card, bought_round: usize, id: usize) -> Self {
+    Self {
+      card,
+      id,
+      bought_round,
+      income: 0,
+      paid_back_round: None,
+      retired_id: None,
+    }
+  }
+
+  /// Whether the card was owned when the dice were rolled. Ids are given out in the order cards
+  /// are bought or change hands, so they order these against the rolls.
+  pub fn owned_at(&self, roll: &DiceRoll) -> bool {
+    self.id < roll.next_card_id && self.retired_id.is_none_or(|id| id >= roll.next_card_id)
+  }
+
+  pub fn add_income(&mut self, coins: u16, round: usize) {
+    self.income += coins;
+    if self.paid_back_round.is_none() && self.income >= self.card.def().cost {
+      self.paid_back_round = Some(round);
+    }
+  }
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct DiceRoll {
+  // Number of dice rolled, 1 or 2
+  pub dice: u8,
+  pub round: usize,
+  // Id of the next card bought or moved after the roll
+  pub next_card_id: usize,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct OwnedLandmark {
   pub landmark: Landmark,
-  pub bought_round: u8,
+  pub bought_round: usize,
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Player {
   pub coins: u16,
   pub cards: Vec<OwnedCard>,
+  // Cards the player exchanged or gave away, with the income they earned until then
+  #[serde(default)]
+  pub retired_cards: Vec<OwnedCard>,
   pub landmarks: Vec<OwnedLandmark>,
-  pub dice_rolls: Vec<(u8, u8)>, // (dice roll - 1 or 2, round)
+  pub dice_rolls: Vec<DiceRoll>,
+  pub forfeited: bool, // Forfeited players take no more turns and cannot win
 }
 
 impl Player {
-  pub fn new() -> Self {
+  pub fn new(starting_coins: u16) -> Self {
     Self {
-      coins: 5, // start with 5 coins
+      coins: starting_coins,
       cards: Vec::new(),
+      retired_cards: Vec::new(),
       landmarks: Vec::new(),
       dice_rolls: Vec::new(),
+      forfeited: false,
     }
   }
 
@@ -35,29 +92,48 @@ impl Player {
     self.coins >= card.def().cost
   }
 
-  pub fn can_afford_landmark(&self, landmark: &Landmark) -> bool {
-    if *landmark == Landmark::LoanOffice && self.landmarks.len() != 0 {
+  /// A player can own at most 1 purple card of each type
+  pub fn can_own_card(&self, card: &Card) -> bool {
+    card.def().color != CardColor::Purple
+      || !self
+        .cards
+        .iter()
+        .any(|OwnedCard { card: owned, .. }| owned == card)
+  }
+
+  pub fn can_afford_landmark(&self, landmark: &Landmark, active_landmarks: &[Landmark]) -> bool {
+    if *landmark == Landmark::LoanOffice && !self.landmarks.is_empty() {
       return false;
     }
-    self.coins >= landmark.def().cost[self.landmarks.len()]
+    self.coins >= get_landmark_cost(self, active_landmarks, *landmark)
   }
 
-  pub fn buy_card(&mut self, card: Card, bought_round: u8) {
+  pub fn buy_card(&mut self, card: Card, bought_round: usize, id: usize) -> Result<(), GameError> {
     if !self.can_afford_card(&card) {
-      panic!("Player cannot afford card");
+      return Err(GameError::CannotAffordCard(card));
+    }
+    if !self.can_own_card(&card) {
+      return Err(GameError::PurpleCardAlreadyOwned(card));
     }
-    self.cards.push(OwnedCard { card, bought_round });
+    self.cards.push(OwnedCard::new(card, bought_round, id));
     self.coins -= card.def().cost;
+    Ok(())
   }
 
-  pub fn buy_landmark(&mut self, landmark: Landmark, bought_round: u8) {
-    if !self.can_afford_landmark(&landmark) {
-      panic!("Player cannot afford landmark");
+  pub fn buy_landmark(
+    &mut self,
+    landmark: Landmark,
+    bought_round: usize,
+    active_landmarks: &[Landmark],
+  ) -> Result<(), GameError> {
+    if !self.can_afford_landmark(&landmark, active_landmarks) {
+      return Err(GameError::CannotAffordLandmark(landmark));
     }
-    self.coins -= landmark.def().cost[self.landmarks.len()];
+    self.coins -= get_landmark_cost(self, active_landmarks, landmark);
     self.landmarks.push(OwnedLandmark {
       landmark,
       bought_round,
     });
+    Ok(())
   }
 }
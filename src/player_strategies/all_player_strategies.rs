@@ -22,6 +22,19 @@ pub fn get_player_strategy(strategy_type: PlayerStrategyType) -> Box<dyn PlayerS
   }
 }
 
+/// Same as get_player_strategy, but any randomness in the strategy is seeded
+pub fn get_player_strategy_with_seed(
+  strategy_type: PlayerStrategyType,
+  seed: u64,
+) -> Box<dyn PlayerStrategy> {
+  match strategy_type {
+    PlayerStrategyType::Random => Box::new(RandomStrategy::new_with_seed(seed)),
+    PlayerStrategyType::LandmarkRush => Box::new(LandmarkRushStrategy::new_with_seed(seed)),
+    PlayerStrategyType::GreedyBestCard => Box::new(GreedyBestCardStrategy::new_with_seed(seed)),
+    PlayerStrategyType::Optimized => Box::new(OptimizedStrategy::new_with_seed(seed)),
+  }
+}
+
 pub fn get_random_player_strategy() -> Box<dyn PlayerStrategy> {
   let mut rng = rand::thread_rng();
   let strategies = PlayerStrategyType::iter().collect::<Vec<PlayerStrategyType>>();
@@ -19,7 +19,7 @@ impl Game {
     for card in self
       .less_than_7_face_up
       .keys()
-      .filter(|card| self.current_player_can_afford_card(card))
+      .filter(|card| self.current_player_can_buy_card(card))
     {
       cards.push(*card);
     }
@@ -27,11 +27,14 @@ impl Game {
     for card in self
       .greater_than_6_face_up
       .keys()
-      .filter(|card| self.current_player_can_afford_card(card))
+      .filter(|card| self.current_player_can_buy_card(card))
     {
       cards.push(*card);
     }
 
+    // Face-up cards are stored in HashMaps with random iteration order. Sort them so that
+    // strategies see the same order in every run, which keeps seeded games reproducible.
+    cards.sort_unstable_by_key(|card| *card as usize);
     cards
   }
 
@@ -48,6 +51,30 @@ impl Game {
     landmarks
   }
 
+  /// List of (player's card, opponent index, opponent's card) exchanges that are legal
+  pub fn get_legal_exchanges(&self) -> Vec<(Card, usize, Card)> {
+    let opponents_cards = self.get_opponents_cards();
+    let mut exchanges = Vec::new();
+    for owned in self.players[self.current_player].cards.iter() {
+      for (opponent_card, opponent_index) in opponents_cards.iter() {
+        if self.can_exchange_establishment(owned.card, *opponent_index, *opponent_card) {
+          exchanges.push((owned.card, *opponent_index, *opponent_card));
+        }
+      }
+    }
+    exchanges
+  }
+
+  /// List of cards that the player is allowed to give to the player on the right
+  pub fn get_giveable_cards(&self) -> Vec<Card> {
+    self.players[self.current_player]
+      .cards
+      .iter()
+      .map(|owned| owned.card)
+      .filter(|card| self.can_give_establishment_to_right(*card))
+      .collect()
+  }
+
   /// List of cards that the opponents own, along with their index in the players array
   pub fn get_opponents_cards(&self) -> Vec<(Card, usize)> {
     let mut cards = Vec::with_capacity(MAX_OPPONENTS_CARDS);
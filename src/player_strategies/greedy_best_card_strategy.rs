@@ -10,26 +10,51 @@ use crate::player_strategies::RandomStrategy;
 
 pub struct GreedyBestCardStrategy {
   random: RandomStrategy, // Composition: contains a RandomStrategy
+  // Cards to buy when no landmark is affordable, most wanted first
+  priority: Vec<Card>,
+}
+
+impl Default for GreedyBestCardStrategy {
+  fn default() -> Self {
+    Self::new()
+  }
 }
 
 impl GreedyBestCardStrategy {
   pub fn new() -> Self {
     Self {
       random: RandomStrategy::new(),
+      priority: DEFAULT_PRIORITY.to_vec(),
     }
   }
+
+  pub fn new_with_seed(seed: u64) -> Self {
+    Self {
+      random: RandomStrategy::new_with_seed(seed),
+      priority: DEFAULT_PRIORITY.to_vec(),
+    }
+  }
+
+  /// Set the cards to buy when no landmark is affordable, most wanted first
+  pub fn set_priority(&mut self, priority: Vec<Card>) {
+    self.priority = priority;
+  }
 }
 
-const CARDS_TO_BUY: &[Card] = &[Card::ShoppingDistrict, Card::Vineyard, Card::FlowerGarden];
+pub const DEFAULT_PRIORITY: &[Card] = &[Card::ShoppingDistrict, Card::Vineyard, Card::FlowerGarden];
 
 impl PlayerStrategy for GreedyBestCardStrategy {
+  fn name(&self) -> &'static str {
+    "GreedyBestCard"
+  }
+
   // Override: Buy landmark first if available, then buy the best card
   fn decide_purchase(&mut self, game: &Game) -> PurchaseDecision {
     if let Some(landmark) = game.get_affordable_landmarks().first() {
       return PurchaseDecision::BuyLandmark(*landmark);
     }
     let affordable_cards: HashSet<Card> = game.get_affordable_cards().into_iter().collect();
-    for card in CARDS_TO_BUY {
+    for card in self.priority.iter() {
       if affordable_cards.contains(card) {
         return PurchaseDecision::BuyCard(*card);
       }
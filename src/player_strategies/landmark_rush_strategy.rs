@@ -1,7 +1,4 @@
-use std::collections::HashSet;
-
 use crate::game::Game;
-use crate::models::Card;
 use crate::player_strategies::player_strategy::{
   DiceRollDecision, ExchangeEstablishmentDecision, GiveEstablishmentDecision, PlayerStrategy,
   PurchaseDecision,
@@ -12,15 +9,31 @@ pub struct LandmarkRushStrategy {
   random: RandomStrategy,
 }
 
+impl Default for LandmarkRushStrategy {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
 impl LandmarkRushStrategy {
   pub fn new() -> Self {
     Self {
       random: RandomStrategy::new(),
     }
   }
+
+  pub fn new_with_seed(seed: u64) -> Self {
+    Self {
+      random: RandomStrategy::new_with_seed(seed),
+    }
+  }
 }
 
 impl PlayerStrategy for LandmarkRushStrategy {
+  fn name(&self) -> &'static str {
+    "LandmarkRush"
+  }
+
   // Override: Buy landmark first if available
   fn decide_purchase(&mut self, game: &Game) -> PurchaseDecision {
     if let Some(landmark) = game.get_affordable_landmarks().first() {
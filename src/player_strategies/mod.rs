@@ -5,9 +5,11 @@ pub mod landmark_rush_strategy;
 pub mod optimized_strategy;
 pub mod player_strategy;
 pub mod random_strategy;
+pub mod registry;
 
 pub use greedy_best_card_strategy::GreedyBestCardStrategy;
 pub use landmark_rush_strategy::LandmarkRushStrategy;
 pub use optimized_strategy::OptimizedStrategy;
 pub use player_strategy::PlayerStrategy;
 pub use random_strategy::RandomStrategy;
+pub use registry::{StrategyError, StrategyRegistry, StrategySpec};
@@ -1,5 +1,4 @@
 use rand::rngs::StdRng;
-use rand::seq::SliceRandom;
 use rand::{Rng, SeedableRng};
 
 use crate::player_strategies::player_strategy::{
@@ -17,16 +16,32 @@ pub struct OptimizedStrategy {
   rng: StdRng,
 }
 
+impl Default for OptimizedStrategy {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
 impl OptimizedStrategy {
   pub fn new() -> Self {
     Self {
       rng: StdRng::from_entropy(),
     }
   }
+
+  pub fn new_with_seed(seed: u64) -> Self {
+    Self {
+      rng: StdRng::seed_from_u64(seed),
+    }
+  }
 }
 
 impl PlayerStrategy for OptimizedStrategy {
-  fn decide_dice_roll(&mut self, game: &Game) -> DiceRollDecision {
+  fn name(&self) -> &'static str {
+    "Optimized"
+  }
+
+  fn decide_dice_roll(&mut self, _game: &Game) -> DiceRollDecision {
     if self.rng.gen_bool(0.5) {
       DiceRollDecision::RollOne
     } else {
@@ -35,17 +50,20 @@ impl PlayerStrategy for OptimizedStrategy {
   }
 
   // TODO placeholder
-  fn decide_purchase(&mut self, game: &Game) -> PurchaseDecision {
+  fn decide_purchase(&mut self, _game: &Game) -> PurchaseDecision {
     PurchaseDecision::BuyNothing
   }
 
   // TODO placeholder
-  fn decide_exchange_establishment(&mut self, game: &Game) -> ExchangeEstablishmentDecision {
+  fn decide_exchange_establishment(&mut self, _game: &Game) -> ExchangeEstablishmentDecision {
     ExchangeEstablishmentDecision::NoExchange
   }
 
-  // TODO placeholder
+  // TODO placeholder. Giving is mandatory, so give the first card that can be given.
   fn decide_give_establishment(&mut self, game: &Game) -> GiveEstablishmentDecision {
-    GiveEstablishmentDecision::NoGive
+    match game.get_giveable_cards().first() {
+      Some(card) => GiveEstablishmentDecision::Give(*card),
+      None => GiveEstablishmentDecision::NoGive,
+    }
   }
 }
@@ -56,6 +56,7 @@ impl fmt::Display for ExchangeEstablishmentDecision {
   }
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum GiveEstablishmentDecision {
   Give(Card),
   NoGive, // Should only happen when player has no cards which should happen very rarely
@@ -73,6 +74,10 @@ impl fmt::Display for GiveEstablishmentDecision {
 /// Trait (interface) that all player strategies must implement
 /// This defines the contract that any player strategy must fulfill
 pub trait PlayerStrategy {
+  /// Name used to identify the strategy in records and reports
+  fn name(&self) -> &'static str {
+    "Custom"
+  }
   fn decide_dice_roll(&mut self, game: &Game) -> DiceRollDecision;
   fn decide_purchase(&mut self, game: &Game) -> PurchaseDecision;
   fn decide_exchange_establishment(&mut self, game: &Game) -> ExchangeEstablishmentDecision;
@@ -12,16 +12,32 @@ pub struct RandomStrategy {
   rng: StdRng,
 }
 
+impl Default for RandomStrategy {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
 impl RandomStrategy {
   pub fn new() -> Self {
     Self {
       rng: StdRng::from_entropy(),
     }
   }
+
+  pub fn new_with_seed(seed: u64) -> Self {
+    Self {
+      rng: StdRng::seed_from_u64(seed),
+    }
+  }
 }
 
 impl PlayerStrategy for RandomStrategy {
-  fn decide_dice_roll(&mut self, game: &Game) -> DiceRollDecision {
+  fn name(&self) -> &'static str {
+    "Random"
+  }
+
+  fn decide_dice_roll(&mut self, _game: &Game) -> DiceRollDecision {
     if self.rng.gen_bool(0.5) {
       DiceRollDecision::RollOne
     } else {
@@ -47,7 +63,8 @@ impl PlayerStrategy for RandomStrategy {
   }
 
   /// Player chooses a random card of their own or no card. If a card is chosen, then the player
-  /// chooses a random card from any of the opponents. Each option has equal probability.
+  /// chooses a random card from any of the opponents that can legally be exchanged for it.
+  /// Each option has equal probability.
   fn decide_exchange_establishment(&mut self, game: &Game) -> ExchangeEstablishmentDecision {
     let current_player = &game.players[game.current_player];
     if current_player.cards.is_empty() {
@@ -65,22 +82,25 @@ impl PlayerStrategy for RandomStrategy {
       ..
     } = current_player.cards[choice_index];
 
-    let (opponent_card, opponent_index) =
-      *game.get_opponents_cards().choose(&mut self.rng).unwrap();
+    let legal_exchanges = game
+      .get_legal_exchanges()
+      .into_iter()
+      .filter(|(card, _, _)| *card == card_to_exchange)
+      .collect::<Vec<_>>();
 
-    ExchangeEstablishmentDecision::Exchange(card_to_exchange, opponent_index, opponent_card)
+    match legal_exchanges.choose(&mut self.rng) {
+      Some((card, opponent_index, opponent_card)) => {
+        ExchangeEstablishmentDecision::Exchange(*card, *opponent_index, *opponent_card)
+      }
+      None => ExchangeEstablishmentDecision::NoExchange,
+    }
   }
 
   /// Player chooses a random card of their own to give.
   fn decide_give_establishment(&mut self, game: &Game) -> GiveEstablishmentDecision {
-    if game.players[game.current_player].cards.is_empty() {
-      return GiveEstablishmentDecision::NoGive;
+    match game.get_giveable_cards().choose(&mut self.rng) {
+      Some(card) => GiveEstablishmentDecision::Give(*card),
+      None => GiveEstablishmentDecision::NoGive,
     }
-
-    let owned_card = game.players[game.current_player]
-      .cards
-      .choose(&mut self.rng)
-      .unwrap();
-    GiveEstablishmentDecision::Give(owned_card.card)
   }
 }
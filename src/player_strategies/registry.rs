@@ -0,0 +1,383 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::models::Card;
+use crate::player_strategies::all_player_strategies::{
+  get_player_strategy_with_seed, PlayerStrategyType,
+};
+use crate::player_strategies::{GreedyBestCardStrategy, PlayerStrategy};
+use crate::rng::Rng;
+
+/// Errors of parsing strategy specs and building strategies from them
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StrategyError {
+  // (Strategy spec, reason)
+  InvalidSpec(String, String),
+  // Strategy name that is not in the registry
+  UnknownStrategy(String),
+}
+
+impl fmt::Display for StrategyError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      StrategyError::InvalidSpec(spec, reason) => {
+        write!(f, "Invalid strategy '{}': {}", spec, reason)
+      }
+      StrategyError::UnknownStrategy(name) => write!(f, "Unknown strategy '{}'", name),
+    }
+  }
+}
+
+impl std::error::Error for StrategyError {}
+
+/// A strategy name with optional parameters, written as `name:key=value:key=value`.
+/// For example `greedy:priority=ShoppingDistrict,Vineyard` or `random:seed=7`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct StrategySpec {
+  pub name: String,
+  pub params: Vec<(String, String)>,
+}
+
+impl StrategySpec {
+  pub fn new(name: &str) -> Self {
+    Self {
+      name: name.to_string(),
+      params: Vec::new(),
+    }
+  }
+
+  pub fn get(&self, key: &str) -> Option<&str> {
+    self
+      .params
+      .iter()
+      .find(|(param, _)| param == key)
+      .map(|(_, value)| value.as_str())
+  }
+
+  /// Parse the value of a parameter, None if the parameter is not given
+  pub fn parse<T: FromStr>(&self, key: &str) -> Result<Option<T>, StrategyError> {
+    match self.get(key) {
+      Some(value) => value
+        .parse::<T>()
+        .map(Some)
+        .map_err(|_| self.error(&format!("invalid value '{}' for '{}'", value, key))),
+      None => Ok(None),
+    }
+  }
+
+  /// Parse a comma separated list parameter, None if the parameter is not given
+  pub fn parse_list<T: FromStr>(&self, key: &str) -> Result<Option<Vec<T>>, StrategyError> {
+    match self.get(key) {
+      Some(values) => values
+        .split(',')
+        .map(|value| {
+          value
+            .parse::<T>()
+            .map_err(|_| self.error(&format!("invalid value '{}' for '{}'", value, key)))
+        })
+        .collect::<Result<Vec<T>, StrategyError>>()
+        .map(Some),
+      None => Ok(None),
+    }
+  }
+
+  /// Fails if the spec has a parameter that is not in the allowed list
+  pub fn allow_params(&self, allowed: &[&str]) -> Result<(), StrategyError> {
+    match self
+      .params
+      .iter()
+      .find(|(key, _)| !allowed.contains(&key.as_str()))
+    {
+      Some((key, _)) => Err(self.error(&format!("unknown parameter '{}'", key))),
+      None => Ok(()),
+    }
+  }
+
+  pub fn error(&self, reason: &str) -> StrategyError {
+    StrategyError::InvalidSpec(self.to_string(), reason.to_string())
+  }
+}
+
+impl FromStr for StrategySpec {
+  type Err = StrategyError;
+
+  fn from_str(text: &str) -> Result<Self, Self::Err> {
+    let mut parts = text.split(':');
+    let name = parts.next().unwrap_or_default().trim();
+    if name.is_empty() {
+      return Err(StrategyError::InvalidSpec(
+        text.to_string(),
+        "missing strategy name".to_string(),
+      ));
+    }
+    let mut spec = StrategySpec::new(name);
+    for part in parts {
+      match part.split_once('=') {
+        Some((key, value)) => spec
+          .params
+          .push((key.trim().to_string(), value.trim().to_string())),
+        None => {
+          return Err(StrategyError::InvalidSpec(
+            text.to_string(),
+            format!("expected key=value, got '{}'", part),
+          ))
+        }
+      }
+    }
+    Ok(spec)
+  }
+}
+
+impl fmt::Display for StrategySpec {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{}", self.name)?;
+    for (key, value) in self.params.iter() {
+      write!(f, ":{}={}", key, value)?;
+    }
+    Ok(())
+  }
+}
+
+impl TryFrom<String> for StrategySpec {
+  type Error = StrategyError;
+
+  fn try_from(text: String) -> Result<Self, Self::Error> {
+    text.parse()
+  }
+}
+
+impl From<StrategySpec> for String {
+  fn from(spec: StrategySpec) -> Self {
+    spec.to_string()
+  }
+}
+
+/// Builds a strategy from a spec. The seed is the seat seed chosen by the caller, with the `seed`
+/// parameter of the spec mixed in if given, so every game still gets its own strategy seed.
+pub type StrategyConstructor =
+  fn(&StrategySpec, u64) -> Result<Box<dyn PlayerStrategy>, StrategyError>;
+
+/// Maps strategy names to constructors. Other crates can register their own strategies and then
+/// use them in simulator lineups like the built-in ones.
+#[derive(Clone)]
+pub struct StrategyRegistry {
+  constructors: Vec<(&'static str, StrategyConstructor)>,
+}
+
+impl Default for StrategyRegistry {
+  fn default() -> Self {
+    Self::with_builtin_strategies()
+  }
+}
+
+impl StrategyRegistry {
+  /// Creates a registry without any strategies
+  pub fn new() -> Self {
+    Self {
+      constructors: Vec::new(),
+    }
+  }
+
+  /// Creates a registry with random, landmark, greedy and optimized. The full type names
+  /// (e.g. GreedyBestCard) work as well.
+  pub fn with_builtin_strategies() -> Self {
+    let mut registry = Self::new();
+    registry.register("random", build_random);
+    registry.register("landmark", build_landmark_rush);
+    registry.register("greedy", build_greedy_best_card);
+    registry.register("optimized", build_optimized);
+    registry.register("LandmarkRush", build_landmark_rush);
+    registry.register("GreedyBestCard", build_greedy_best_card);
+    registry
+  }
+
+  /// Register a strategy under a name. Names are case insensitive, a later registration with the
+  /// same name replaces the earlier one.
+  pub fn register(&mut self, name: &'static str, constructor: StrategyConstructor) {
+    self
+      .constructors
+      .retain(|(registered, _)| !registered.eq_ignore_ascii_case(name));
+    self.constructors.push((name, constructor));
+  }
+
+  pub fn names(&self) -> Vec<&'static str> {
+    self.constructors.iter().map(|(name, _)| *name).collect()
+  }
+
+  pub fn build(
+    &self,
+    spec: &StrategySpec,
+    seat_seed: u64,
+  ) -> Result<Box<dyn PlayerStrategy>, StrategyError> {
+    let constructor = self
+      .constructors
+      .iter()
+      .find(|(name, _)| name.eq_ignore_ascii_case(&spec.name))
+      .map(|(_, constructor)| constructor)
+      .ok_or_else(|| StrategyError::UnknownStrategy(spec.name.clone()))?;
+    let seed = match spec.parse::<u64>("seed")? {
+      Some(spec_seed) => Rng::derive_seed(seat_seed, spec_seed),
+      None => seat_seed,
+    };
+    constructor(spec, seed)
+  }
+}
+
+fn build_random(spec: &StrategySpec, seed: u64) -> Result<Box<dyn PlayerStrategy>, StrategyError> {
+  spec.allow_params(&["seed"])?;
+  Ok(get_player_strategy_with_seed(
+    PlayerStrategyType::Random,
+    seed,
+  ))
+}
+
+fn build_landmark_rush(
+  spec: &StrategySpec,
+  seed: u64,
+) -> Result<Box<dyn PlayerStrategy>, StrategyError> {
+  spec.allow_params(&["seed"])?;
+  Ok(get_player_strategy_with_seed(
+    PlayerStrategyType::LandmarkRush,
+    seed,
+  ))
+}
+
+fn build_greedy_best_card(
+  spec: &StrategySpec,
+  seed: u64,
+) -> Result<Box<dyn PlayerStrategy>, StrategyError> {
+  spec.allow_params(&["seed", "priority"])?;
+  let mut strategy = GreedyBestCardStrategy::new_with_seed(seed);
+  if let Some(priority) = spec.parse_list::<Card>("priority")? {
+    strategy.set_priority(priority);
+  }
+  Ok(Box::new(strategy))
+}
+
+fn build_optimized(
+  spec: &StrategySpec,
+  seed: u64,
+) -> Result<Box<dyn PlayerStrategy>, StrategyError> {
+  spec.allow_params(&["seed"])?;
+  Ok(get_player_strategy_with_seed(
+    PlayerStrategyType::Optimized,
+    seed,
+  ))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn parses_name_and_params() {
+    let spec: StrategySpec = "greedy:priority=ShoppingDistrict,Vineyard:seed=7"
+      .parse()
+      .unwrap();
+    assert_eq!(spec.name, "greedy");
+    assert_eq!(spec.get("priority"), Some("ShoppingDistrict,Vineyard"));
+    assert_eq!(spec.parse::<u64>("seed"), Ok(Some(7)));
+    assert_eq!(spec.parse::<u64>("missing"), Ok(None));
+    assert_eq!(
+      spec.parse_list::<Card>("priority"),
+      Ok(Some(vec![Card::ShoppingDistrict, Card::Vineyard]))
+    );
+  }
+
+  #[test]
+  fn display_round_trips() {
+    for text in [
+      "random",
+      "random:seed=7",
+      "greedy:priority=Cafe,Bakery:seed=1",
+    ] {
+      let spec: StrategySpec = text.parse().unwrap();
+      assert_eq!(spec.to_string(), text);
+      assert_eq!(spec.to_string().parse::<StrategySpec>(), Ok(spec));
+    }
+  }
+
+  #[test]
+  fn rejects_invalid_specs() {
+    assert_eq!(
+      "".parse::<StrategySpec>(),
+      Err(StrategyError::InvalidSpec(
+        "".to_string(),
+        "missing strategy name".to_string()
+      ))
+    );
+    assert_eq!(
+      ":seed=7".parse::<StrategySpec>(),
+      Err(StrategyError::InvalidSpec(
+        ":seed=7".to_string(),
+        "missing strategy name".to_string()
+      ))
+    );
+    assert_eq!(
+      "random:seed".parse::<StrategySpec>(),
+      Err(StrategyError::InvalidSpec(
+        "random:seed".to_string(),
+        "expected key=value, got 'seed'".to_string()
+      ))
+    );
+  }
+
+  #[test]
+  fn rejects_invalid_values() {
+    let spec: StrategySpec = "random:seed=abc".parse().unwrap();
+    assert_eq!(
+      spec.parse::<u64>("seed"),
+      Err(StrategyError::InvalidSpec(
+        "random:seed=abc".to_string(),
+        "invalid value 'abc' for 'seed'".to_string()
+      ))
+    );
+    let spec: StrategySpec = "greedy:priority=Cafe,Nothing".parse().unwrap();
+    assert!(spec.parse_list::<Card>("priority").is_err());
+  }
+
+  #[test]
+  fn builds_registered_strategies() {
+    let registry = StrategyRegistry::with_builtin_strategies();
+    for text in [
+      "random",
+      "landmark",
+      "greedy:priority=Cafe",
+      "optimized:seed=3",
+      "GreedyBestCard",
+      "RANDOM",
+    ] {
+      let spec: StrategySpec = text.parse().unwrap();
+      assert!(registry.build(&spec, 0).is_ok(), "{} does not build", text);
+    }
+  }
+
+  #[test]
+  fn build_rejects_unknown_strategies_and_params() {
+    let registry = StrategyRegistry::with_builtin_strategies();
+    let build = |text: &str| registry.build(&text.parse().unwrap(), 0).err();
+    assert_eq!(
+      build("nobody"),
+      Some(StrategyError::UnknownStrategy("nobody".to_string()))
+    );
+    assert_eq!(
+      build("random:priority=Cafe"),
+      Some(StrategyError::InvalidSpec(
+        "random:priority=Cafe".to_string(),
+        "unknown parameter 'priority'".to_string()
+      ))
+    );
+    assert!(build("optimized:seed=-1").is_some());
+  }
+
+  #[test]
+  fn later_registration_replaces_earlier_one() {
+    let mut registry = StrategyRegistry::new();
+    registry.register("random", build_random);
+    registry.register("Random", build_landmark_rush);
+    assert_eq!(registry.names(), vec!["Random"]);
+  }
+}
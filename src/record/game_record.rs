@@ -0,0 +1,366 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::config::GameConfig;
+use crate::error::GameError;
+use crate::models::{Card, Landmark};
+use crate::player_strategies::player_strategy::{
+  DiceRollDecision, ExchangeEstablishmentDecision, GiveEstablishmentDecision, PurchaseDecision,
+};
+use crate::player_strategies::StrategySpec;
+use crate::rules::validation::IllegalMovePolicy;
+
+const HEADER: &str = "machi-koro-record v2";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Decision {
+  DiceRoll(DiceRollDecision),
+  Purchase(PurchaseDecision),
+  Exchange(ExchangeEstablishmentDecision),
+  Give(GiveEstablishmentDecision),
+}
+
+/// A decision made by the strategy in the given seat
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordedDecision {
+  pub seat: usize,
+  pub decision: Decision,
+}
+
+/// Everything needed to replay a game: the game is fully determined by the seed, the rules and
+/// the decisions of the strategies.
+///
+/// The text format has one entry per line:
+/// ```text
+/// machi-koro-record v2
+/// seed 42
+/// policy Substitute
+/// config {"starting_coins":5,"buy_only_turns":3,...,"deck_composition":[["SushiBar",5]]}
+/// player random:seed=7
+/// 0 roll 2
+/// 0 buy card WheatField
+/// 1 buy landmark Airport
+/// 1 buy nothing
+/// 2 exchange Cafe 1 Bakery
+/// 3 give none
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GameRecord {
+  pub seed: u64,
+  pub config: GameConfig,
+  pub illegal_move_policy: IllegalMovePolicy,
+  // Strategy spec of every seat
+  pub lineup: Vec<StrategySpec>,
+  pub decisions: Vec<RecordedDecision>,
+}
+
+#[derive(Debug)]
+pub enum RecordError {
+  Io(std::io::Error),
+  Json(serde_json::Error),
+  // (line number, message)
+  Parse(usize, String),
+  Game(GameError),
+  // Replay asked for a decision that does not match the record (turn)
+  Diverged(usize),
+}
+
+impl fmt::Display for RecordError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      RecordError::Io(error) => write!(f, "{}", error),
+      RecordError::Json(error) => write!(f, "{}", error),
+      RecordError::Parse(line, message) => write!(f, "Line {}: {}", line, message),
+      RecordError::Game(error) => write!(f, "{}", error),
+      RecordError::Diverged(turn) => write!(f, "Replay diverged from the record in turn {}", turn),
+    }
+  }
+}
+
+impl std::error::Error for RecordError {}
+
+impl From<std::io::Error> for RecordError {
+  fn from(error: std::io::Error) -> Self {
+    RecordError::Io(error)
+  }
+}
+
+impl From<serde_json::Error> for RecordError {
+  fn from(error: serde_json::Error) -> Self {
+    RecordError::Json(error)
+  }
+}
+
+impl From<GameError> for RecordError {
+  fn from(error: GameError) -> Self {
+    RecordError::Game(error)
+  }
+}
+
+impl GameRecord {
+  pub fn save(&self, path: &str) -> Result<(), RecordError> {
+    std::fs::write(path, self.to_string())?;
+    Ok(())
+  }
+
+  pub fn load(path: &str) -> Result<Self, RecordError> {
+    std::fs::read_to_string(path)?.parse()
+  }
+}
+
+fn card_name(card: &Card) -> &'static str {
+  card.into()
+}
+
+fn landmark_name(landmark: &Landmark) -> &'static str {
+  landmark.into()
+}
+
+impl fmt::Display for GameRecord {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    writeln!(f, "{}", HEADER)?;
+    writeln!(f, "seed {}", self.seed)?;
+    let policy: &'static str = self.illegal_move_policy.into();
+    writeln!(f, "policy {}", policy)?;
+    // The whole config as JSON on one line, so no field can be left out
+    let config = serde_json::to_string(&self.config).map_err(|_| fmt::Error)?;
+    writeln!(f, "config {}", config)?;
+    for spec in self.lineup.iter() {
+      writeln!(f, "player {}", spec)?;
+    }
+    for RecordedDecision { seat, decision } in self.decisions.iter() {
+      write!(f, "{} ", seat)?;
+      match decision {
+        Decision::DiceRoll(DiceRollDecision::RollOne) => writeln!(f, "roll 1")?,
+        Decision::DiceRoll(DiceRollDecision::RollTwo) => writeln!(f, "roll 2")?,
+        Decision::Purchase(PurchaseDecision::BuyCard(card)) => {
+          writeln!(f, "buy card {}", card_name(card))?
+        }
+        Decision::Purchase(PurchaseDecision::BuyLandmark(landmark)) => {
+          writeln!(f, "buy landmark {}", landmark_name(landmark))?
+        }
+        Decision::Purchase(PurchaseDecision::BuyNothing) => writeln!(f, "buy nothing")?,
+        Decision::Exchange(ExchangeEstablishmentDecision::Exchange(
+          card,
+          opponent_index,
+          opponent_card,
+        )) => writeln!(
+          f,
+          "exchange {} {} {}",
+          card_name(card),
+          opponent_index,
+          card_name(opponent_card)
+        )?,
+        Decision::Exchange(ExchangeEstablishmentDecision::NoExchange) => {
+          writeln!(f, "exchange none")?
+        }
+        Decision::Give(GiveEstablishmentDecision::Give(card)) => {
+          writeln!(f, "give {}", card_name(card))?
+        }
+        Decision::Give(GiveEstablishmentDecision::NoGive) => writeln!(f, "give none")?,
+      }
+    }
+    Ok(())
+  }
+}
+
+fn parse_value<T: FromStr>(value: Option<&str>, line: usize) -> Result<T, RecordError> {
+  let value = value.ok_or_else(|| RecordError::Parse(line, "Missing value".to_string()))?;
+  value
+    .parse::<T>()
+    .map_err(|_| RecordError::Parse(line, format!("Invalid value '{}'", value)))
+}
+
+fn parse_decision(
+  mut words: std::str::SplitWhitespace,
+  line: usize,
+) -> Result<Decision, RecordError> {
+  let invalid = || RecordError::Parse(line, "Invalid decision".to_string());
+  let decision = match (words.next(), words.next()) {
+    (Some("roll"), Some("1")) => Decision::DiceRoll(DiceRollDecision::RollOne),
+    (Some("roll"), Some("2")) => Decision::DiceRoll(DiceRollDecision::RollTwo),
+    (Some("buy"), Some("card")) => {
+      Decision::Purchase(PurchaseDecision::BuyCard(parse_value(words.next(), line)?))
+    }
+    (Some("buy"), Some("landmark")) => Decision::Purchase(PurchaseDecision::BuyLandmark(
+      parse_value(words.next(), line)?,
+    )),
+    (Some("buy"), Some("nothing")) => Decision::Purchase(PurchaseDecision::BuyNothing),
+    (Some("exchange"), Some("none")) => {
+      Decision::Exchange(ExchangeEstablishmentDecision::NoExchange)
+    }
+    (Some("exchange"), card) => Decision::Exchange(ExchangeEstablishmentDecision::Exchange(
+      parse_value(card, line)?,
+      parse_value(words.next(), line)?,
+      parse_value(words.next(), line)?,
+    )),
+    (Some("give"), Some("none")) => Decision::Give(GiveEstablishmentDecision::NoGive),
+    (Some("give"), card) => {
+      Decision::Give(GiveEstablishmentDecision::Give(parse_value(card, line)?))
+    }
+    _ => return Err(invalid()),
+  };
+  if words.next().is_some() {
+    return Err(invalid());
+  }
+  Ok(decision)
+}
+
+impl FromStr for GameRecord {
+  type Err = RecordError;
+
+  fn from_str(text: &str) -> Result<Self, Self::Err> {
+    let mut lines = text
+      .lines()
+      .enumerate()
+      .filter(|(_, line)| !line.trim().is_empty());
+    match lines.next() {
+      Some((_, line)) if line.trim() == HEADER => {}
+      _ => return Err(RecordError::Parse(1, format!("Expected '{}'", HEADER))),
+    }
+
+    let mut record = GameRecord {
+      seed: 0,
+      config: GameConfig::default(),
+      illegal_move_policy: IllegalMovePolicy::default(),
+      lineup: Vec::new(),
+      decisions: Vec::new(),
+    };
+    for (index, line) in lines {
+      let line_number = index + 1;
+      let mut words = line.split_whitespace();
+      match words.next() {
+        Some("seed") => record.seed = parse_value(words.next(), line_number)?,
+        Some("policy") => record.illegal_move_policy = parse_value(words.next(), line_number)?,
+        Some("player") => record.lineup.push(parse_value(words.next(), line_number)?),
+        Some("config") => {
+          let json = line.trim_start()["config".len()..].trim();
+          record.config = serde_json::from_str(json)
+            .map_err(|error| RecordError::Parse(line_number, error.to_string()))?;
+        }
+        Some(seat) => {
+          let seat = parse_value::<usize>(Some(seat), line_number)?;
+          let decision = parse_decision(words, line_number)?;
+          record.decisions.push(RecordedDecision { seat, decision });
+        }
+        None => {}
+      }
+    }
+    Ok(record)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn record() -> GameRecord {
+    // Every field differs from the default, so a field left out of the text would show up
+    GameRecord {
+      seed: 42,
+      config: GameConfig {
+        starting_coins: 7,
+        buy_only_turns: 1,
+        landmarks_to_win: 4,
+        face_up_cards: 6,
+        face_up_landmarks: 3,
+        min_players: 3,
+        max_players: 5,
+        coins_when_broke: 2,
+        max_rounds: 300,
+        stalemate_rounds: 0,
+        deck_composition: vec![(Card::Cafe, 4), (Card::BusinessCenter, 2)],
+      },
+      illegal_move_policy: IllegalMovePolicy::Forfeit,
+      lineup: vec![
+        "random:seed=7".parse().unwrap(),
+        "greedy:priority=ShoppingDistrict,Vineyard".parse().unwrap(),
+        StrategySpec::new("landmark"),
+      ],
+      decisions: [
+        (0, Decision::DiceRoll(DiceRollDecision::RollOne)),
+        (1, Decision::DiceRoll(DiceRollDecision::RollTwo)),
+        (
+          2,
+          Decision::Purchase(PurchaseDecision::BuyCard(Card::WheatField)),
+        ),
+        (
+          0,
+          Decision::Purchase(PurchaseDecision::BuyLandmark(Landmark::Airport)),
+        ),
+        (1, Decision::Purchase(PurchaseDecision::BuyNothing)),
+        (
+          2,
+          Decision::Exchange(ExchangeEstablishmentDecision::Exchange(
+            Card::Cafe,
+            1,
+            Card::Bakery,
+          )),
+        ),
+        (
+          0,
+          Decision::Exchange(ExchangeEstablishmentDecision::NoExchange),
+        ),
+        (
+          1,
+          Decision::Give(GiveEstablishmentDecision::Give(Card::Mine)),
+        ),
+        (2, Decision::Give(GiveEstablishmentDecision::NoGive)),
+      ]
+      .into_iter()
+      .map(|(seat, decision)| RecordedDecision { seat, decision })
+      .collect(),
+    }
+  }
+
+  #[test]
+  fn text_round_trips() {
+    let record = record();
+    let text = record.to_string();
+    assert!(text.starts_with(HEADER));
+    assert_eq!(text.parse::<GameRecord>().unwrap(), record);
+  }
+
+  #[test]
+  fn ignores_blank_lines() {
+    let text = record().to_string().replace('\n', "\n\n");
+    assert_eq!(text.parse::<GameRecord>().unwrap(), record());
+  }
+
+  fn parse_error(text: &str) -> Option<(usize, String)> {
+    match text.parse::<GameRecord>() {
+      Err(RecordError::Parse(line, message)) => Some((line, message)),
+      _ => None,
+    }
+  }
+
+  #[test]
+  fn rejects_missing_header() {
+    assert_eq!(
+      parse_error("seed 1"),
+      Some((1, format!("Expected '{}'", HEADER)))
+    );
+  }
+
+  #[test]
+  fn rejects_invalid_lines() {
+    let text = |line: &str| format!("{}\nseed 1\n{}\n", HEADER, line);
+    assert_eq!(
+      parse_error(&text("0 roll 3")),
+      Some((3, "Invalid decision".to_string()))
+    );
+    assert_eq!(
+      parse_error(&text("0 buy nothing now")),
+      Some((3, "Invalid decision".to_string()))
+    );
+    assert_eq!(
+      parse_error(&text("0 buy card Nothing")),
+      Some((3, "Invalid value 'Nothing'".to_string()))
+    );
+    assert_eq!(
+      parse_error(&text("player random:seed")),
+      Some((3, "Invalid value 'random:seed'".to_string()))
+    );
+    assert!(parse_error(&text("config {\"starting_coins\":5}")).is_some());
+  }
+}
@@ -0,0 +1,8 @@
+mod game_record;
+mod recording;
+mod replay;
+mod snapshot;
+
+pub use game_record::{Decision, GameRecord, RecordError, RecordedDecision};
+pub use recording::{DecisionLog, RecordingStrategy};
+pub use replay::{replay, ScriptedStrategy};
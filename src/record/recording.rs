@@ -0,0 +1,63 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::game::Game;
+use crate::player_strategies::player_strategy::{
+  DiceRollDecision, ExchangeEstablishmentDecision, GiveEstablishmentDecision, PurchaseDecision,
+};
+use crate::player_strategies::PlayerStrategy;
+use crate::record::game_record::{Decision, RecordedDecision};
+
+/// Decisions of all seats in the order they were made
+pub type DecisionLog = Rc<RefCell<Vec<RecordedDecision>>>;
+
+/// Wraps a strategy and logs every decision it makes. The raw decision is logged (before the
+/// referee sees it), so illegal decisions are replayed the same way.
+pub struct RecordingStrategy {
+  inner: Box<dyn PlayerStrategy>,
+  seat: usize,
+  log: DecisionLog,
+}
+
+impl RecordingStrategy {
+  pub fn new(inner: Box<dyn PlayerStrategy>, seat: usize, log: DecisionLog) -> Self {
+    Self { inner, seat, log }
+  }
+
+  fn record(&self, decision: Decision) {
+    self.log.borrow_mut().push(RecordedDecision {
+      seat: self.seat,
+      decision,
+    });
+  }
+}
+
+impl PlayerStrategy for RecordingStrategy {
+  fn name(&self) -> &'static str {
+    self.inner.name()
+  }
+
+  fn decide_dice_roll(&mut self, game: &Game) -> DiceRollDecision {
+    let decision = self.inner.decide_dice_roll(game);
+    self.record(Decision::DiceRoll(decision.clone()));
+    decision
+  }
+
+  fn decide_purchase(&mut self, game: &Game) -> PurchaseDecision {
+    let decision = self.inner.decide_purchase(game);
+    self.record(Decision::Purchase(decision));
+    decision
+  }
+
+  fn decide_exchange_establishment(&mut self, game: &Game) -> ExchangeEstablishmentDecision {
+    let decision = self.inner.decide_exchange_establishment(game);
+    self.record(Decision::Exchange(decision.clone()));
+    decision
+  }
+
+  fn decide_give_establishment(&mut self, game: &Game) -> GiveEstablishmentDecision {
+    let decision = self.inner.decide_give_establishment(game);
+    self.record(Decision::Give(decision.clone()));
+    decision
+  }
+}
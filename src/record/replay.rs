@@ -0,0 +1,181 @@
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::rc::Rc;
+
+use crate::engine::Engine;
+use crate::error::GameError;
+use crate::game::Game;
+use crate::player_strategies::player_strategy::{
+  DiceRollDecision, ExchangeEstablishmentDecision, GiveEstablishmentDecision, PurchaseDecision,
+};
+use crate::player_strategies::PlayerStrategy;
+use crate::record::game_record::{Decision, GameRecord, RecordError, RecordedDecision};
+
+/// Plays back the recorded decisions of one seat. All seats share the same script so the order
+/// of the decisions is checked as well. When the next decision does not belong to this seat or
+/// is of the wrong kind, the replay has diverged and a harmless decision is made instead.
+pub struct ScriptedStrategy {
+  seat: usize,
+  script: Rc<RefCell<VecDeque<RecordedDecision>>>,
+  diverged: Rc<Cell<bool>>,
+}
+
+impl ScriptedStrategy {
+  pub fn new(
+    seat: usize,
+    script: Rc<RefCell<VecDeque<RecordedDecision>>>,
+    diverged: Rc<Cell<bool>>,
+  ) -> Self {
+    Self {
+      seat,
+      script,
+      diverged,
+    }
+  }
+
+  /// Takes the next decision if it belongs to this seat and matches the expected kind
+  fn next<T>(&mut self, extract: fn(Decision) -> Option<T>) -> Option<T> {
+    let mut script = self.script.borrow_mut();
+    let decision = match script.front() {
+      Some(RecordedDecision { seat, decision }) if *seat == self.seat => extract(decision.clone()),
+      _ => None,
+    };
+    match decision {
+      Some(_) => {
+        script.pop_front();
+      }
+      None => self.diverged.set(true),
+    }
+    decision
+  }
+}
+
+impl PlayerStrategy for ScriptedStrategy {
+  fn name(&self) -> &'static str {
+    "Scripted"
+  }
+
+  fn decide_dice_roll(&mut self, _game: &Game) -> DiceRollDecision {
+    self
+      .next(|decision| match decision {
+        Decision::DiceRoll(decision) => Some(decision),
+        _ => None,
+      })
+      .unwrap_or(DiceRollDecision::RollOne)
+  }
+
+  fn decide_purchase(&mut self, _game: &Game) -> PurchaseDecision {
+    self
+      .next(|decision| match decision {
+        Decision::Purchase(decision) => Some(decision),
+        _ => None,
+      })
+      .unwrap_or(PurchaseDecision::BuyNothing)
+  }
+
+  fn decide_exchange_establishment(&mut self, _game: &Game) -> ExchangeEstablishmentDecision {
+    self
+      .next(|decision| match decision {
+        Decision::Exchange(decision) => Some(decision),
+        _ => None,
+      })
+      .unwrap_or(ExchangeEstablishmentDecision::NoExchange)
+  }
+
+  fn decide_give_establishment(&mut self, game: &Game) -> GiveEstablishmentDecision {
+    self
+      .next(|decision| match decision {
+        Decision::Give(decision) => Some(decision),
+        _ => None,
+      })
+      .unwrap_or_else(|| match game.get_giveable_cards().first() {
+        Some(card) => GiveEstablishmentDecision::Give(*card),
+        None => GiveEstablishmentDecision::NoGive,
+      })
+  }
+}
+
+/// Replays a recorded game and returns the state of the game before the first turn and after
+/// every turn. The last state is the finished game.
+pub fn replay(record: &GameRecord) -> Result<Vec<Game>, RecordError> {
+  let player_count = record.lineup.len();
+  if player_count < record.config.min_players || player_count > record.config.max_players {
+    return Err(GameError::InvalidPlayerCount(player_count).into());
+  }
+
+  let script = Rc::new(RefCell::new(
+    record.decisions.iter().cloned().collect::<VecDeque<_>>(),
+  ));
+  let diverged = Rc::new(Cell::new(false));
+
+  let mut engine = Engine::new(record.config.clone(), Some(record.seed));
+  engine.set_illegal_move_policy(record.illegal_move_policy);
+  for seat in 0..player_count {
+    engine.add_player_strategy(Box::new(ScriptedStrategy::new(
+      seat,
+      script.clone(),
+      diverged.clone(),
+    )));
+  }
+
+  let mut games = vec![engine.game.clone()];
+  while engine.game.outcome().is_none() {
+    let turn = engine.game.current_turn;
+    engine.play_turn()?;
+    if diverged.get() {
+      return Err(RecordError::Diverged(turn));
+    }
+    games.push(engine.game.clone());
+  }
+
+  // Decisions left over means the recorded game went on longer than the replay
+  if !script.borrow().is_empty() {
+    return Err(RecordError::Diverged(engine.game.current_turn));
+  }
+  Ok(games)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::config::GameConfig;
+  use crate::player_strategies::{StrategyRegistry, StrategySpec};
+
+  #[test]
+  fn replay_of_saved_record_ends_in_the_same_state() {
+    let registry = StrategyRegistry::with_builtin_strategies();
+    let mut engine = Engine::new(GameConfig::default(), Some(11));
+    for (seat, text) in ["random:seed=3", "greedy", "landmark", "optimized"]
+      .iter()
+      .enumerate()
+    {
+      let spec = text.parse().unwrap();
+      let strategy = registry.build(&spec, seat as u64).unwrap();
+      engine.add_player_strategy_with_spec(strategy, spec);
+    }
+    engine.start_recording();
+    engine.run().unwrap();
+
+    let record: GameRecord = engine.get_record().unwrap().to_string().parse().unwrap();
+    let games = replay(&record).unwrap();
+    let state = |game: &Game| serde_json::to_string(game).unwrap();
+    assert_eq!(state(games.last().unwrap()), state(&engine.game));
+  }
+
+  #[test]
+  fn replay_detects_divergence() {
+    let mut engine = Engine::new(GameConfig::default(), Some(11));
+    let registry = StrategyRegistry::with_builtin_strategies();
+    for seat in 0..2 {
+      let spec = StrategySpec::new("random");
+      engine.add_player_strategy_with_spec(registry.build(&spec, seat).unwrap(), spec);
+    }
+    engine.start_recording();
+    engine.run().unwrap();
+
+    let mut record = engine.get_record().unwrap();
+    // The first decision is made by seat 0, giving it to seat 1 breaks the order
+    record.decisions[0].seat = 1;
+    assert!(matches!(replay(&record), Err(RecordError::Diverged(_))));
+  }
+}
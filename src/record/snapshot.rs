@@ -0,0 +1,35 @@
+use crate::engine::Engine;
+use crate::game::Game;
+use crate::player_strategies::PlayerStrategy;
+use crate::record::game_record::RecordError;
+
+/// Saving and loading of mid-game positions as JSON
+impl Game {
+  pub fn to_json(&self) -> Result<String, RecordError> {
+    Ok(serde_json::to_string_pretty(self)?)
+  }
+
+  pub fn from_json(json: &str) -> Result<Self, RecordError> {
+    Ok(serde_json::from_str(json)?)
+  }
+
+  pub fn save(&self, path: &str) -> Result<(), RecordError> {
+    std::fs::write(path, self.to_json()?)?;
+    Ok(())
+  }
+
+  pub fn load(path: &str) -> Result<Self, RecordError> {
+    Game::from_json(&std::fs::read_to_string(path)?)
+  }
+}
+
+impl Engine {
+  /// Continues a saved game. There must be one strategy for each player of the game. The game
+  /// only continues identically if the strategies make the same decisions as before.
+  pub fn from_game(game: Game, player_strategies: Vec<Box<dyn PlayerStrategy>>) -> Self {
+    let mut engine = Engine::new(game.config.clone(), Some(game.seed));
+    engine.game = game;
+    engine.player_strategies = player_strategies;
+    engine
+  }
+}
@@ -1,18 +1,52 @@
-use rand::rngs::StdRng;
 use rand::seq::SliceRandom;
 use rand::{Rng as RandRng, SeedableRng};
+use rand_chacha::ChaCha12Rng;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone)]
+/// ChaCha12Rng is the generator behind rand's StdRng, used directly because it exposes its
+/// position in the stream, which makes the state serializable.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(from = "RngState", into = "RngState")]
 pub struct Rng {
-  rng: StdRng,
+  rng: ChaCha12Rng,
   seed: u64,
 }
 
+/// Serialized form of the RNG: the seed and the number of 32-bit words consumed so far
+#[derive(Serialize, Deserialize)]
+struct RngState {
+  seed: u64,
+  word_pos: u128,
+}
+
+impl From<Rng> for RngState {
+  fn from(rng: Rng) -> Self {
+    Self {
+      seed: rng.seed,
+      word_pos: rng.rng.get_word_pos(),
+    }
+  }
+}
+
+impl From<RngState> for Rng {
+  fn from(state: RngState) -> Self {
+    let mut rng = Rng::new_with_seed(state.seed);
+    rng.rng.set_word_pos(state.word_pos);
+    rng
+  }
+}
+
+impl Default for Rng {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
 impl Rng {
   /// Create a new RNG with a seed
   pub fn new_with_seed(seed: u64) -> Self {
     Self {
-      rng: StdRng::seed_from_u64(seed),
+      rng: ChaCha12Rng::seed_from_u64(seed),
       seed,
     }
   }
@@ -21,11 +55,20 @@ impl Rng {
   pub fn new() -> Self {
     let seed = rand::thread_rng().gen::<u64>();
     Self {
-      rng: StdRng::seed_from_u64(seed),
+      rng: ChaCha12Rng::seed_from_u64(seed),
       seed,
     }
   }
 
+  /// Derive an independent seed from a parent seed and an index (splitmix64). Used to give
+  /// every game and every strategy its own reproducible seed from a single master seed.
+  pub fn derive_seed(seed: u64, index: u64) -> u64 {
+    let mut z = seed ^ index.wrapping_add(1).wrapping_mul(0x9E3779B97F4A7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+    z ^ (z >> 31)
+  }
+
   pub fn get_seed(&self) -> u64 {
     self.seed
   }
@@ -1,48 +1,59 @@
+use crate::error::GameError;
+use crate::events::CoinSource;
 use crate::game::Game;
 use crate::models::card::CardEffect;
-use crate::models::{Card, CardCategory, CardColor, CardDef};
+use crate::models::Card;
 use crate::player_strategies::player_strategy::ExchangeEstablishmentDecision;
 use crate::player_strategies::PlayerStrategy;
+use crate::rules::landmark::get_total_card_earnings_bonus;
+use crate::rules::validation::Referee;
 
+/// Applies the effect of a card and returns the coins it earned its owner
 pub fn activate_card(
   card: Card,
   game: &mut Game,
   owner_index: usize,
   player_strategy: &mut dyn PlayerStrategy,
-) {
+  referee: &mut Referee,
+) -> Result<u16, GameError> {
+  let coins_before = game.players[owner_index].coins;
   let def = card.def();
+  // Passive landmarks (e.g. Farmers Market) add a bonus to the earnings of the card
+  let bonus = get_total_card_earnings_bonus(game.get_active_landmarks(), &def.category);
+  let source = CoinSource::Card(card);
   match def.effect {
     CardEffect::TakeCoinsFromActivePlayer(amount) => {
-      game.take_coins_from_active_player(owner_index, amount);
+      game.take_coins_from_active_player(owner_index, amount + bonus, source);
     }
     CardEffect::TakeCoinsFromEachOpponent(amount) => {
-      game.take_coins_from_each_opponent(owner_index, amount);
+      game.take_coins_from_each_opponent(owner_index, amount + bonus, source);
     }
     CardEffect::TakeCoinsFromEachOpponentWithMoreThan10Coins => {
-      game.take_coins_from_opponents_with_more_than_10_coins(owner_index);
+      game.take_coins_from_opponents_with_more_than_10_coins(owner_index, source);
     }
     CardEffect::GetCoinsFromBank(amount) => {
-      game.get_coins_from_bank(owner_index, amount);
+      game.get_coins_from_bank(owner_index, amount + bonus, source);
     }
     CardEffect::GetCoinsFromBankForEachCardCategory(amount, category) => {
-      game.get_coins_from_bank_for_each_card_category(owner_index, amount, category);
+      game.get_coins_from_bank_for_each_card_category(owner_index, amount, category, bonus, source);
     }
     CardEffect::GetCoinsFromBankForEachCardColor(amount, color) => {
-      game.get_coins_from_bank_for_each_card_color(owner_index, amount, color);
+      game.get_coins_from_bank_for_each_card_color(owner_index, amount, color, bonus, source);
     }
     CardEffect::ExchangeEstablishment => {
       let decision = player_strategy.decide_exchange_establishment(game);
-      match decision {
+      match referee.check_exchange(game, decision)? {
         ExchangeEstablishmentDecision::Exchange(card, opponent_index, opponent_card) => {
-          game.exchange_establishment(card, opponent_index, opponent_card);
+          game.exchange_establishment(card, opponent_index, opponent_card)?;
         }
         ExchangeEstablishmentDecision::NoExchange => {}
       }
     }
   }
+  Ok(game.players[owner_index].coins.saturating_sub(coins_before))
 }
 
-const DECK_COMPOSITION: &[(Card, u8)] = &[
+pub const DECK_COMPOSITION: &[(Card, u8)] = &[
   (Card::SushiBar, 5),
   (Card::WheatField, 5),
   (Card::Vineyard, 5),
@@ -65,24 +76,24 @@ const DECK_COMPOSITION: &[(Card, u8)] = &[
   (Card::FoodWarehouse, 3),
 ];
 
-pub fn build_less_than_7_deck() -> Vec<Card> {
-  DECK_COMPOSITION
+pub fn build_less_than_7_deck(deck_composition: &[(Card, u8)]) -> Vec<Card> {
+  deck_composition
     .iter()
     .filter(|(card, _copies)| {
       let def = card.def();
       def.activation.iter().all(|&activation| activation <= 6)
     })
-    .flat_map(|(card, copies)| std::iter::repeat(card.clone()).take(*copies as usize))
+    .flat_map(|(card, copies)| std::iter::repeat_n(*card, *copies as usize))
     .collect()
 }
 
-pub fn build_greater_than_6_deck() -> Vec<Card> {
-  DECK_COMPOSITION
+pub fn build_greater_than_6_deck(deck_composition: &[(Card, u8)]) -> Vec<Card> {
+  deck_composition
     .iter()
     .filter(|(card, _copies)| {
       let def = card.def();
       def.activation.iter().all(|&activation| activation > 6)
     })
-    .flat_map(|(card, copies)| std::iter::repeat(card.clone()).take(*copies as usize))
+    .flat_map(|(card, copies)| std::iter::repeat_n(*card, *copies as usize))
     .collect()
 }
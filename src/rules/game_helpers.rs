@@ -1,3 +1,5 @@
+use crate::error::GameError;
+use crate::events::{CoinSource, GameEvent};
 use crate::game::Game;
 use crate::models::player::OwnedCard;
 use crate::models::{Card, CardCategory, CardColor, CardDef};
@@ -6,90 +8,143 @@ use crate::models::{Card, CardCategory, CardColor, CardDef};
 /// Used by both card and landmark rules
 impl Game {
   #[inline]
-  pub fn get_coins_from_bank(&mut self, owner_index: usize, amount: u16) {
+  pub fn get_coins_from_bank(&mut self, owner_index: usize, amount: u16, source: CoinSource) {
     self.players[owner_index].coins += amount;
+    if amount > 0 {
+      self.emit(GameEvent::BankTransfer {
+        player_index: owner_index,
+        coins: amount as i32,
+        source,
+      });
+    }
   }
 
+  /// The bonus of the activated card is added once, not for every counted card
   #[inline]
   pub fn get_coins_from_bank_for_each_card_category(
     &mut self,
     owner_index: usize,
     amount: u16,
     category: CardCategory,
+    bonus: u16,
+    source: CoinSource,
   ) {
-    let coins_to_get: u16 = self.players[owner_index]
+    let card_count = self.players[owner_index]
       .cards
       .iter()
       .filter(|OwnedCard { card, .. }| card.def().category == category)
-      .map(|_card| amount)
-      .sum();
-    self.players[owner_index].coins += coins_to_get;
+      .count() as u16;
+    self.get_coins_from_bank(owner_index, amount * card_count + bonus, source);
   }
 
+  /// The bonus of the activated card is added once, not for every counted card
   #[inline]
   pub fn get_coins_from_bank_for_each_card_color(
     &mut self,
     owner_index: usize,
     amount: u16,
     color: CardColor,
+    bonus: u16,
+    source: CoinSource,
   ) {
-    let coins_to_get: u16 = self.players[owner_index]
+    let card_count = self.players[owner_index]
       .cards
       .iter()
       .filter(|OwnedCard { card, .. }| card.def().color == color)
-      .map(|_card| amount)
-      .sum();
-    self.players[owner_index].coins += coins_to_get;
+      .count() as u16;
+    self.get_coins_from_bank(owner_index, amount * card_count + bonus, source);
   }
 
+  /// All coins are pooled and shared evenly, rounded up. The pool goes through the bank, which
+  /// also pays for the rounding.
   #[inline]
-  pub fn redistribute_coins_evenly(&mut self) {
-    let total_coins: u16 = self.players.iter().map(|player| player.coins as u16).sum();
+  pub fn redistribute_coins_evenly(&mut self, source: CoinSource) {
+    let total_coins: u16 = self.players.iter().map(|player| player.coins).sum();
     let num_players = self.players.len() as u16;
-    let coins_per_player = ((total_coins + num_players - 1) / num_players) as u16;
-    for player in self.players.iter_mut() {
-      player.coins = coins_per_player;
+    let coins_per_player = total_coins.div_ceil(num_players);
+    for player_index in 0..self.players.len() {
+      let change = coins_per_player as i32 - self.players[player_index].coins as i32;
+      self.players[player_index].coins = coins_per_player;
+      if change != 0 {
+        self.emit(GameEvent::BankTransfer {
+          player_index,
+          coins: change,
+          source,
+        });
+      }
     }
   }
 
   #[inline]
-  fn move_coins_between_players(&mut self, from_index: usize, to_index: usize, amount: u16) {
+  fn move_coins_between_players(
+    &mut self,
+    from_index: usize,
+    to_index: usize,
+    amount: u16,
+    source: CoinSource,
+  ) {
     let from_coins = self.players[from_index].coins;
     let coins_to_move = std::cmp::min(from_coins, amount);
     self.players[from_index].coins -= coins_to_move;
     self.players[to_index].coins += coins_to_move;
+    if coins_to_move > 0 {
+      self.emit(GameEvent::CoinTransfer {
+        from_index,
+        to_index,
+        coins: coins_to_move,
+        source,
+      });
+    }
   }
 
   #[inline]
-  pub fn take_coins_from_active_player(&mut self, owner_index: usize, amount: u16) {
-    self.move_coins_between_players(self.current_player as usize, owner_index, amount);
+  pub fn take_coins_from_active_player(
+    &mut self,
+    owner_index: usize,
+    amount: u16,
+    source: CoinSource,
+  ) {
+    self.move_coins_between_players(self.current_player, owner_index, amount, source);
   }
 
   #[inline]
-  pub fn take_coins_from_each_opponent(&mut self, owner_index: usize, amount: u16) {
+  pub fn take_coins_from_each_opponent(
+    &mut self,
+    owner_index: usize,
+    amount: u16,
+    source: CoinSource,
+  ) {
     for player_index in 0..self.players.len() {
       if player_index != owner_index {
-        self.move_coins_between_players(player_index, owner_index, amount);
+        self.move_coins_between_players(player_index, owner_index, amount, source);
       }
     }
   }
 
   #[inline]
-  pub fn take_coins_from_opponents_with_more_than_10_coins(&mut self, owner_index: usize) {
+  pub fn take_coins_from_opponents_with_more_than_10_coins(
+    &mut self,
+    owner_index: usize,
+    source: CoinSource,
+  ) {
     for player_index in 0..self.players.len() {
       if player_index != owner_index && self.players[player_index].coins > 10 {
         let coins_to_take = self.players[player_index].coins / 2;
-        self.move_coins_between_players(player_index, owner_index, coins_to_take);
+        self.move_coins_between_players(player_index, owner_index, coins_to_take, source);
       }
     }
   }
 
   #[inline]
-  pub fn take_coins_from_each_opponent_for_each_landmark(&mut self, owner_index: usize) {
+  pub fn take_coins_from_each_opponent_for_each_landmark(
+    &mut self,
+    owner_index: usize,
+    source: CoinSource,
+  ) {
     for player_index in 0..self.players.len() {
       if player_index != owner_index {
         let landmarks_owned = self.players[player_index].landmarks.len() as u16;
-        self.move_coins_between_players(player_index, owner_index, 3 * landmarks_owned);
+        self.move_coins_between_players(player_index, owner_index, 3 * landmarks_owned, source);
       }
     }
   }
@@ -100,6 +155,7 @@ impl Game {
     owner_index: usize,
     amount: u16,
     predicate: fn(&CardDef) -> bool,
+    source: CoinSource,
   ) {
     for player_index in 0..self.players.len() {
       if player_index != owner_index {
@@ -109,39 +165,90 @@ impl Game {
           .filter(|OwnedCard { card, .. }| predicate(&card.def()))
           .map(|_card| amount)
           .sum();
-        self.move_coins_between_players(player_index, owner_index, coins_to_take);
+        self.move_coins_between_players(player_index, owner_index, coins_to_take, source);
       }
     }
   }
 
   #[inline]
-  fn move_cards_between_players(&mut self, from_index: usize, to_index: usize, card_to_move: Card) {
+  fn move_cards_between_players(
+    &mut self,
+    from_index: usize,
+    to_index: usize,
+    card_to_move: Card,
+  ) -> Result<(), GameError> {
     let card_index = self.players[from_index]
       .cards
       .iter()
       .position(|OwnedCard { card, .. }| *card == card_to_move)
-      .unwrap();
-    self.players[from_index].cards.remove(card_index);
+      .ok_or(GameError::CardNotOwned(from_index, card_to_move))?;
+    let mut retired = self.players[from_index].cards.remove(card_index);
+    // The old owner keeps the history of the card with the income it earned them, the card
+    // starts over for its new owner as if bought this round
+    let id = self.new_card_id();
+    retired.retired_id = Some(id);
+    self.players[from_index].retired_cards.push(retired);
+    let bought_round = self.get_round();
+    self.players[to_index]
+      .cards
+      .push(OwnedCard::new(card_to_move, bought_round, id));
+    Ok(())
+  }
 
-    let bought_round = self.get_round() as u8;
-    self.players[to_index].cards.push(OwnedCard {
-      card: card_to_move,
-      bought_round, // TODO moved round
-    });
+  /// Credits coins earned by an activation to the owned card that was activated. A card that an
+  /// exchange earlier in the same activation phase moved away is found in the retired cards of
+  /// its owner.
+  #[inline]
+  pub fn add_card_income(&mut self, owner_index: usize, card_id: usize, coins: u16) {
+    let round = self.get_round();
+    let owner = &mut self.players[owner_index];
+    if let Some(owned) = owner
+      .cards
+      .iter_mut()
+      .chain(owner.retired_cards.iter_mut())
+      .find(|owned| owned.id == card_id)
+    {
+      owned.add_income(coins, round);
+    }
   }
 
   #[inline]
-  pub fn exchange_establishment(&mut self, card: Card, opponent_index: usize, opponent_card: Card) {
-    self.move_cards_between_players(self.current_player as usize, opponent_index, card);
-    self.move_cards_between_players(opponent_index, self.current_player as usize, opponent_card);
+  pub fn exchange_establishment(
+    &mut self,
+    card: Card,
+    opponent_index: usize,
+    opponent_card: Card,
+  ) -> Result<(), GameError> {
+    if !self.can_exchange_establishment(card, opponent_index, opponent_card) {
+      return Err(GameError::IllegalExchange(
+        card,
+        opponent_index,
+        opponent_card,
+      ));
+    }
+    self.move_cards_between_players(self.current_player, opponent_index, card)?;
+    self.move_cards_between_players(opponent_index, self.current_player, opponent_card)?;
+    self.emit(GameEvent::EstablishmentExchanged {
+      player_index: self.current_player,
+      card,
+      opponent_index,
+      opponent_card,
+    });
+    Ok(())
   }
 
   #[inline]
-  pub fn give_establishment_to_right(&mut self, card: Card) {
-    self.move_cards_between_players(
-      self.current_player as usize,
-      (self.players.len() + self.current_player - 1) % self.players.len(),
+  pub fn give_establishment_to_right(&mut self, card: Card) -> Result<(), GameError> {
+    if !self.can_give_establishment_to_right(card) {
+      return Err(GameError::IllegalGive(card));
+    }
+    let to_index = self.player_to_the_right();
+    self.move_cards_between_players(self.current_player, to_index, card)?;
+    self.emit(GameEvent::EstablishmentGiven {
+      player_index: self.current_player,
+      to_index,
       card,
-    );
+    });
+    Ok(())
   }
 }
@@ -1,140 +1,135 @@
+use crate::error::GameError;
+use crate::events::CoinSource;
 use crate::game::Game;
-use crate::models::{CardCategory, Landmark};
+use crate::models::landmark::LandmarkType;
+use crate::models::player::OwnedLandmark;
+use crate::models::{CardCategory, Landmark, Player};
 use crate::player_strategies::player_strategy::GiveEstablishmentDecision;
 use crate::player_strategies::PlayerStrategy;
+use crate::rules::validation::Referee;
 use strum::IntoEnumIterator;
 
 /// Activate immediate landmarks (one-time effects when built)
 pub fn activate_landmark(landmark: Landmark, game: &mut Game) {
+  let source = CoinSource::Landmark(landmark);
   match landmark {
     Landmark::ExhibitHall => {
-      game.take_coins_from_opponents_with_more_than_10_coins(game.current_player);
+      game.take_coins_from_opponents_with_more_than_10_coins(game.current_player, source);
     }
     Landmark::FrenchRestaurant => {
-      game.take_coins_from_each_opponent(game.current_player, 2);
+      game.take_coins_from_each_opponent(game.current_player, 2, source);
     }
     Landmark::LaunchPad => {
       // you win. do nothing, winning is handled by the engine
     }
     Landmark::Museum => {
-      game.take_coins_from_each_opponent_for_each_landmark(game.current_player);
+      game.take_coins_from_each_opponent_for_each_landmark(game.current_player, source);
     }
     Landmark::Park => {
-      game.redistribute_coins_evenly();
+      game.redistribute_coins_evenly(source);
     }
     Landmark::Publisher => {
-      game.take_coins_from_each_opponent_for_each_card(game.current_player, 1, |card_def| {
-        card_def.category == CardCategory::Bread
-      });
+      game.take_coins_from_each_opponent_for_each_card(
+        game.current_player,
+        1,
+        |card_def| card_def.category == CardCategory::Bread,
+        source,
+      );
     }
     Landmark::RadioTower => {
       game.take_another_turn_after_this_one();
     }
     Landmark::TvStation => {
-      game.take_coins_from_each_opponent_for_each_card(game.current_player, 1, |card_def| {
-        card_def.category == CardCategory::Cup
-      });
+      game.take_coins_from_each_opponent_for_each_card(
+        game.current_player,
+        1,
+        |card_def| card_def.category == CardCategory::Cup,
+        source,
+      );
     }
     _ => {}
   }
 }
 
-// TODO use this
 /// Get bonus coins for card earnings based on passive landmark modifiers
 pub fn get_card_earnings_bonus(landmark: Landmark, card_category: CardCategory) -> u8 {
   match landmark {
-    Landmark::FarmersMarket => {
-      if card_category == CardCategory::Wheat {
-        1
-      } else {
-        0
-      }
-    }
-    Landmark::Forge => {
-      if card_category == CardCategory::Gear {
-        1
-      } else {
-        0
-      }
-    }
-    Landmark::SodaBottlingPlant => {
-      if card_category == CardCategory::Cup {
-        1
-      } else {
-        0
-      }
-    }
-    Landmark::ShoppingMall => {
-      if card_category == CardCategory::Bread {
-        1
-      } else {
-        0
-      }
-    }
+    Landmark::FarmersMarket if card_category == CardCategory::Wheat => 1,
+    Landmark::Forge if card_category == CardCategory::Gear => 1,
+    Landmark::SodaBottlingPlant if card_category == CardCategory::Cup => 1,
+    Landmark::ShoppingMall if card_category == CardCategory::Bread => 1,
     _ => 0,
   }
 }
 
+/// Get total bonus coins for card earnings from all active landmarks.
+/// The bonus is added once to the earnings of each activated card of the given category.
+pub fn get_total_card_earnings_bonus(
+  active_landmarks: &[Landmark],
+  card_category: &CardCategory,
+) -> u16 {
+  active_landmarks
+    .iter()
+    .map(|landmark| get_card_earnings_bonus(*landmark, card_category.clone()) as u16)
+    .sum()
+}
+
 /// Handle trigger-based effects on dice roll
 pub fn on_dice_roll(
   landmark: Landmark,
   game: &mut Game,
   roll: (u8, u8),
   player_strategy: &mut dyn PlayerStrategy,
-) {
+  referee: &mut Referee,
+) -> Result<(), GameError> {
   let (roll1, roll2) = roll;
   let is_doubles = roll1 == roll2;
+  let source = CoinSource::Landmark(landmark);
 
   match landmark {
-    Landmark::AmusementPark => {
-      if is_doubles {
-        game.take_another_turn_after_this_one();
-      }
+    Landmark::AmusementPark if is_doubles => {
+      game.take_another_turn_after_this_one();
     }
-    Landmark::MovingCompany => {
-      if is_doubles {
-        let decision = player_strategy.decide_give_establishment(game);
-        match decision {
-          GiveEstablishmentDecision::Give(card) => game.give_establishment_to_right(card),
-          GiveEstablishmentDecision::NoGive => {}
-        }
+    Landmark::MovingCompany if is_doubles => {
+      let decision = player_strategy.decide_give_establishment(game);
+      match referee.check_give(game, decision)? {
+        GiveEstablishmentDecision::Give(card) => game.give_establishment_to_right(card)?,
+        GiveEstablishmentDecision::NoGive => {}
       }
     }
-    Landmark::TechStartup => {
-      if roll1 + roll2 == 12 {
-        game.get_coins_from_bank(game.current_player, 8);
-      }
+    Landmark::TechStartup if roll1 + roll2 == 12 => {
+      game.get_coins_from_bank(game.current_player, 8, source);
     }
-    Landmark::Temple => {
-      if is_doubles {
-        game.take_coins_from_each_opponent(game.current_player, 2);
-      }
+    Landmark::Temple if is_doubles => {
+      game.take_coins_from_each_opponent(game.current_player, 2, source);
     }
     _ => {}
   }
+  Ok(())
 }
 
 /// Handle trigger-based effects at turn end.
-/// Special handling for Airport landmark
-pub fn on_turn_end(game: &mut Game, built_something: bool) {
-  if built_something && game.get_active_landmarks().contains(&Landmark::Airport) {
-    game.get_coins_from_bank(game.current_player, 5);
+/// Special handling for Airport landmark. Returns the landmark that triggered, if any.
+pub fn on_turn_end(game: &mut Game, built_something: bool) -> Option<Landmark> {
+  let landmark = Landmark::Airport;
+  if built_something && game.get_active_landmarks().contains(&landmark) {
+    game.get_coins_from_bank(game.current_player, 5, CoinSource::Landmark(landmark));
+    return Some(landmark);
   }
+  None
 }
 
 /// Handle trigger-based effects after card activation
-/// Special handling for Charterhouse landmark
-pub fn on_after_card_activation(game: &mut Game, coins_received: bool) {
-  if coins_received
-    && game
-      .get_active_landmarks()
-      .contains(&Landmark::Charterhouse)
-  {
-    game.get_coins_from_bank(game.current_player, 3);
+/// Special handling for Charterhouse landmark. Returns the landmark that triggered, if any.
+pub fn on_after_card_activation(game: &mut Game, coins_received: bool) -> Option<Landmark> {
+  let landmark = Landmark::Charterhouse;
+  if coins_received && game.get_active_landmarks().contains(&landmark) {
+    game.get_coins_from_bank(game.current_player, 3, CoinSource::Landmark(landmark));
+    return Some(landmark);
   }
+  None
 }
 
-// TODO use this
 /// Get landmark build cost reduction
 pub fn get_landmark_cost_reduction(landmark: Landmark, target_landmark: Landmark) -> u8 {
   match landmark {
@@ -143,18 +138,35 @@ pub fn get_landmark_cost_reduction(landmark: Landmark, target_landmark: Landmark
       // cost of landmarks by 2 coins
       2
     }
-    Landmark::Observatory => {
+    Landmark::Observatory
       // reduce build cost of launch pad by 5 coins
-      if target_landmark == Landmark::LaunchPad {
+      if target_landmark == Landmark::LaunchPad => {
         5
-      } else {
-        0
       }
-    }
     _ => 0,
   }
 }
 
+/// Get the price the buyer pays for the target landmark after all cost reductions.
+/// Infinite landmarks (Observatory) reduce the cost for every player once built, while
+/// Loan Office only reduces the cost for the player who owns it.
+pub fn get_landmark_cost(buyer: &Player, active_landmarks: &[Landmark], target: Landmark) -> u16 {
+  // Landmarks past the last price step (possible with house rules) cost the last price
+  let cost = target.def().cost;
+  let base_cost = cost[buyer.landmarks.len().min(cost.len() - 1)];
+  let active_reduction: u16 = active_landmarks
+    .iter()
+    .map(|landmark| get_landmark_cost_reduction(*landmark, target) as u16)
+    .sum();
+  let owned_reduction: u16 = buyer
+    .landmarks
+    .iter()
+    .filter(|OwnedLandmark { landmark, .. }| landmark.def().landmark_type != LandmarkType::Infinite)
+    .map(|OwnedLandmark { landmark, .. }| get_landmark_cost_reduction(*landmark, target) as u16)
+    .sum();
+  base_cost.saturating_sub(active_reduction + owned_reduction)
+}
+
 pub fn build_landmark_deck() -> Vec<Landmark> {
   Landmark::iter().collect()
 }
@@ -1,3 +1,4 @@
 pub mod card;
 mod game_helpers;
 pub mod landmark;
+pub mod validation;
@@ -0,0 +1,213 @@
+use std::fmt;
+
+use strum::{EnumString, IntoStaticStr};
+
+use crate::error::GameError;
+use crate::game::Game;
+use crate::player_strategies::player_strategy::{
+  ExchangeEstablishmentDecision, GiveEstablishmentDecision, PurchaseDecision,
+};
+
+/// What the engine does when a strategy returns an illegal decision
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, EnumString, IntoStaticStr)]
+pub enum IllegalMovePolicy {
+  // End the game with a GameError::IllegalDecision
+  Panic,
+  // Replace the decision with "do nothing" and continue
+  #[default]
+  Substitute,
+  // The offending player is removed from the game
+  Forfeit,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IllegalDecision {
+  Purchase(PurchaseDecision),
+  Exchange(ExchangeEstablishmentDecision),
+  Give(GiveEstablishmentDecision),
+}
+
+impl fmt::Display for IllegalDecision {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      IllegalDecision::Purchase(decision) => write!(f, "{}", decision),
+      IllegalDecision::Exchange(decision) => write!(f, "{}", decision),
+      IllegalDecision::Give(decision) => write!(f, "{}", decision),
+    }
+  }
+}
+
+/// An illegal decision made by a player strategy
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+  pub player_index: usize,
+  pub round: usize,
+  pub decision: IllegalDecision,
+  pub error: GameError,
+}
+
+impl fmt::Display for Violation {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(
+      f,
+      "Player {} in round {}: {} ({})",
+      self.player_index, self.round, self.decision, self.error
+    )
+  }
+}
+
+/// Legality checks for strategy decisions. These never modify the game.
+impl Game {
+  pub fn validate_purchase(&self, decision: PurchaseDecision) -> Result<(), GameError> {
+    let player = self.get_current_player();
+    match decision {
+      PurchaseDecision::BuyCard(card) => {
+        if !self.less_than_7_face_up.contains_key(&card)
+          && !self.greater_than_6_face_up.contains_key(&card)
+        {
+          return Err(GameError::CardNotAvailable(card));
+        }
+        if !player.can_afford_card(&card) {
+          return Err(GameError::CannotAffordCard(card));
+        }
+        if !player.can_own_card(&card) {
+          return Err(GameError::PurpleCardAlreadyOwned(card));
+        }
+        Ok(())
+      }
+      PurchaseDecision::BuyLandmark(landmark) => {
+        if !self.landmark_face_up.contains(&landmark) {
+          return Err(GameError::LandmarkNotAvailable(landmark));
+        }
+        if !self.current_player_can_afford_landmark(&landmark) {
+          return Err(GameError::CannotAffordLandmark(landmark));
+        }
+        Ok(())
+      }
+      PurchaseDecision::BuyNothing => Ok(()),
+    }
+  }
+
+  pub fn validate_exchange(
+    &self,
+    decision: &ExchangeEstablishmentDecision,
+  ) -> Result<(), GameError> {
+    match *decision {
+      ExchangeEstablishmentDecision::Exchange(card, opponent_index, opponent_card) => {
+        if opponent_index >= self.players.len()
+          || !self.can_exchange_establishment(card, opponent_index, opponent_card)
+        {
+          return Err(GameError::IllegalExchange(
+            card,
+            opponent_index,
+            opponent_card,
+          ));
+        }
+        Ok(())
+      }
+      ExchangeEstablishmentDecision::NoExchange => Ok(()),
+    }
+  }
+
+  /// Giving is mandatory, so NoGive is only legal when there is no card that can be given
+  pub fn validate_give(&self, decision: &GiveEstablishmentDecision) -> Result<(), GameError> {
+    match *decision {
+      GiveEstablishmentDecision::Give(card) => {
+        if !self.can_give_establishment_to_right(card) {
+          return Err(GameError::IllegalGive(card));
+        }
+        Ok(())
+      }
+      GiveEstablishmentDecision::NoGive => {
+        if !self.get_giveable_cards().is_empty() {
+          return Err(GameError::MustGiveEstablishment);
+        }
+        Ok(())
+      }
+    }
+  }
+}
+
+/// Validates strategy decisions against the legal set and applies the illegal move policy.
+/// All violations are recorded so they can be reported in the game result.
+pub struct Referee {
+  pub policy: IllegalMovePolicy,
+  pub violations: Vec<Violation>,
+}
+
+impl Referee {
+  pub fn new(policy: IllegalMovePolicy) -> Self {
+    Self {
+      policy,
+      violations: Vec::new(),
+    }
+  }
+
+  /// Returns the purchase decision that should be applied
+  pub fn check_purchase(
+    &mut self,
+    game: &mut Game,
+    decision: PurchaseDecision,
+  ) -> Result<PurchaseDecision, GameError> {
+    match game.validate_purchase(decision) {
+      Ok(()) => Ok(decision),
+      Err(error) => {
+        self.reject(game, IllegalDecision::Purchase(decision), error)?;
+        Ok(PurchaseDecision::BuyNothing)
+      }
+    }
+  }
+
+  /// Returns the exchange decision that should be applied
+  pub fn check_exchange(
+    &mut self,
+    game: &mut Game,
+    decision: ExchangeEstablishmentDecision,
+  ) -> Result<ExchangeEstablishmentDecision, GameError> {
+    match game.validate_exchange(&decision) {
+      Ok(()) => Ok(decision),
+      Err(error) => {
+        self.reject(game, IllegalDecision::Exchange(decision), error)?;
+        Ok(ExchangeEstablishmentDecision::NoExchange)
+      }
+    }
+  }
+
+  /// Returns the give decision that should be applied
+  pub fn check_give(
+    &mut self,
+    game: &mut Game,
+    decision: GiveEstablishmentDecision,
+  ) -> Result<GiveEstablishmentDecision, GameError> {
+    match game.validate_give(&decision) {
+      Ok(()) => Ok(decision),
+      Err(error) => {
+        self.reject(game, IllegalDecision::Give(decision), error)?;
+        Ok(GiveEstablishmentDecision::NoGive)
+      }
+    }
+  }
+
+  /// Decisions are always made by the current player. Under the Panic policy the violation is
+  /// returned as an error, so the caller decides what to do with the game.
+  fn reject(
+    &mut self,
+    game: &mut Game,
+    decision: IllegalDecision,
+    error: GameError,
+  ) -> Result<(), GameError> {
+    let violation = Violation {
+      player_index: game.current_player,
+      round: game.get_round(),
+      decision,
+      error,
+    };
+    self.violations.push(violation.clone());
+    match self.policy {
+      IllegalMovePolicy::Panic => return Err(GameError::IllegalDecision(Box::new(violation))),
+      IllegalMovePolicy::Substitute => {}
+      IllegalMovePolicy::Forfeit => game.forfeit_player(game.current_player),
+    }
+    Ok(())
+  }
+}
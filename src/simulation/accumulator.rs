@@ -1,208 +1,217 @@
-use crate::engine::MAX_PLAYERS;
-use crate::models::player::OwnedCard;
-use crate::models::Card;
+use serde::Serialize;
+
+use crate::simulation::export::GameRows;
+use crate::simulation::metric::{DynMetric, Metric, MetricSection};
+use crate::simulation::metrics::{
+  CardIncomeMetric, CardPresenceByRoundMetric, CardPresenceMetric, DiceRollMetric, LandmarkMetric,
+  SeatWinMetric, StrategyWinMetric,
+};
 use crate::simulation::simulator::GameResult;
-use std::collections::{HashMap, HashSet};
-use std::sync::Mutex;
-use strum::IntoEnumIterator;
-
-const ROUND_BREAKDOWN: [usize; 3] = [5, 10, 15];
 
+/// Statistics of a simulation run. Every rate comes with its 95% Wilson confidence interval.
+/// Every metric, built-in or added to the simulator, has its own section, the built-in ones come
+/// first.
+#[derive(Serialize)]
 pub struct SimulationResult {
-  pub winner_total_card_counts: HashMap<Card, usize>,
-  pub p_present_win: HashMap<Card, f64>,
-  pub p_present_loss: HashMap<Card, f64>,
-  pub p_present_win_by_round: [(usize, HashMap<Card, f64>); ROUND_BREAKDOWN.len()],
-  pub p_present_loss_by_round: [(usize, HashMap<Card, f64>); ROUND_BREAKDOWN.len()],
-  pub win_count_by_player_index: Vec<usize>,
+  // Games played, draws included
+  pub game_count: usize,
+  pub sections: Vec<MetricSection>,
 }
 
+/// Metrics of a batch of games. Every thread accumulates its own games and the partial
+/// accumulators are merged at the end, so no locks are needed while games are played.
+#[derive(Clone)]
 pub struct SimulationAccumulator {
-  // Total card counts for the winner HashMap<Card, count>
-  pub winner_total_card_counts: Mutex<HashMap<Card, usize>>,
-  // Present card counts for the winner HashMap<Card, count>
-  pub win_present_card_counts: Mutex<HashMap<Card, usize>>,
-  // Present card counts for the other players HashMap<Card, count>
-  pub loss_present_card_counts: Mutex<HashMap<Card, usize>>,
-  // Present card counts for the winner by round (round number, HashMap<Card, count>)
-  pub win_present_card_counts_by_round:
-    Mutex<[(usize, HashMap<Card, usize>); ROUND_BREAKDOWN.len()]>,
-  // Present card counts for the other players by round (round number, HashMap<Card, count>)
-  pub loss_present_card_counts_by_round:
-    Mutex<[(usize, HashMap<Card, usize>); ROUND_BREAKDOWN.len()]>,
-  // Win counts by player index
-  pub win_count_by_player_index: Mutex<Vec<usize>>,
+  pub game_count: usize,
+  // Built-in metrics followed by empty copies of the metrics added to the simulator
+  pub(crate) metrics: Vec<Box<dyn DynMetric>>,
+  // Rows of every game, only kept if asked for
+  pub game_rows: Option<GameRows>,
+  strategies: Vec<String>,
 }
 
 impl SimulationAccumulator {
-  pub fn new() -> Self {
+  pub(crate) fn new(
+    num_players: usize,
+    strategies: Vec<String>,
+    added_metrics: Vec<Box<dyn DynMetric>>,
+  ) -> Self {
+    let mut metrics: Vec<Box<dyn DynMetric>> = vec![
+      Box::new(CardPresenceMetric::new(num_players)),
+      Box::new(CardPresenceByRoundMetric::new(num_players)),
+      Box::new(LandmarkMetric::new(num_players)),
+      Box::new(DiceRollMetric::new(strategies.clone())),
+      Box::new(CardIncomeMetric::new()),
+      Box::new(SeatWinMetric::new(num_players)),
+      Box::new(StrategyWinMetric::new(num_players, strategies.clone())),
+    ];
+    metrics.extend(added_metrics);
     Self {
-      winner_total_card_counts: Mutex::new(HashMap::new()),
-      win_present_card_counts: Mutex::new(HashMap::new()),
-      loss_present_card_counts: Mutex::new(HashMap::new()),
-      win_present_card_counts_by_round: Mutex::new(std::array::from_fn(|i| {
-        (ROUND_BREAKDOWN[i], HashMap::new())
-      })),
-      loss_present_card_counts_by_round: Mutex::new(std::array::from_fn(|i| {
-        (ROUND_BREAKDOWN[i], HashMap::new())
-      })),
-      win_count_by_player_index: Mutex::new(vec![0; MAX_PLAYERS]),
+      game_count: 0,
+      metrics,
+      game_rows: None,
+      strategies,
     }
   }
 
   /// Accumulates the result of a single simulation (game run)
-  pub fn accumulate(&self, result: &GameResult) {
-    self.accumulate_winner_total_card_counts(result);
-    self.accumulate_win_present_card_counts(result);
-    self.accumulate_loss_present_card_counts(result);
-    self.accumulate_win_present_card_counts_by_round(result);
-    self.accumulate_loss_present_card_counts_by_round(result);
-
-    // Track win count by player index
-    let mut win_counts = self.win_count_by_player_index.lock().unwrap();
-    win_counts[result.winner_index] += 1;
+  pub fn accumulate(&mut self, result: &GameResult) {
+    self.game_count += 1;
+    for metric in self.metrics.iter_mut() {
+      metric.observe(result);
+    }
+    if let Some(rows) = self.game_rows.as_mut() {
+      rows.add(result, &self.strategies);
+    }
   }
 
-  fn accumulate_winner_total_card_counts(&self, result: &GameResult) {
-    let mut total = self.winner_total_card_counts.lock().unwrap();
-    for OwnedCard { card, .. } in result.player_cards[result.winner_index].iter() {
-      *total.entry(*card).or_insert(0) += 1;
+  /// Adds the games of another accumulator of the same simulator
+  pub fn merge(mut self, other: Self) -> Self {
+    self.game_count += other.game_count;
+    for (metric, other_metric) in self.metrics.iter_mut().zip(other.metrics) {
+      metric.merge_boxed(other_metric);
     }
+    if let (Some(rows), Some(other_rows)) = (self.game_rows.as_mut(), other.game_rows) {
+      rows.extend(other_rows);
+    }
+    self
   }
 
-  fn accumulate_win_present_card_counts(&self, result: &GameResult) {
-    let mut presence = self.win_present_card_counts.lock().unwrap();
-    let distinct_cards = result.player_cards[result.winner_index]
-      .iter()
-      .map(|OwnedCard { card, .. }| card)
-      .collect::<HashSet<&Card>>();
-    for card in distinct_cards {
-      *presence.entry(*card).or_insert(0) += 1;
+  /// Card and landmark probabilities are computed over the games that had a winner
+  pub fn finalize(self) -> SimulationResult {
+    SimulationResult {
+      game_count: self.game_count,
+      sections: self
+        .metrics
+        .into_iter()
+        .map(|metric| metric.finalize_boxed())
+        .collect(),
     }
   }
 
-  fn accumulate_loss_present_card_counts(&self, result: &GameResult) {
-    let mut loss_presence = self.loss_present_card_counts.lock().unwrap();
-    for player_index in 0..result.player_cards.len() {
-      if player_index == result.winner_index {
-        continue;
-      }
-      let distinct_cards = result.player_cards[player_index]
-        .iter()
-        .map(|OwnedCard { card, .. }| card)
-        .collect::<HashSet<&Card>>();
-      for card in distinct_cards {
-        *loss_presence.entry(*card).or_insert(0) += 1;
-      }
-    }
+  pub(crate) fn card_presence(&self) -> &CardPresenceMetric {
+    self.built_in_metric()
   }
 
-  fn accumulate_win_present_card_counts_by_round(&self, result: &GameResult) {
-    let mut win_present_card_counts_by_round =
-      self.win_present_card_counts_by_round.lock().unwrap();
-    for (index, round) in ROUND_BREAKDOWN.iter().enumerate() {
-      let filtered_distinct_cards = result.player_cards[result.winner_index]
-        .iter()
-        .filter(|OwnedCard { bought_round, .. }| *bought_round < *round as u8)
-        .map(|OwnedCard { card, .. }| card)
-        .collect::<HashSet<&Card>>();
-      for card in filtered_distinct_cards {
-        *win_present_card_counts_by_round[index]
-          .1
-          .entry(*card)
-          .or_insert(0) += 1;
-      }
-    }
+  pub(crate) fn strategy_wins(&self) -> &StrategyWinMetric {
+    self.built_in_metric()
   }
 
-  fn accumulate_loss_present_card_counts_by_round(&self, result: &GameResult) {
-    let mut loss_present_card_counts_by_round =
-      self.loss_present_card_counts_by_round.lock().unwrap();
-    for (index, round) in ROUND_BREAKDOWN.iter().enumerate() {
-      for player_index in 0..result.player_cards.len() {
-        if player_index == result.winner_index {
-          continue;
+  fn built_in_metric<M: Metric>(&self) -> &M {
+    self
+      .metrics
+      .iter()
+      .find_map(|metric| metric.as_any().downcast_ref::<M>())
+      .expect("Built-in metric is missing")
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use serde_json::Value;
+
+  use super::*;
+  use crate::simulation::metrics::CoinFlowMetric;
+  use crate::simulation::simulator::Simulator;
+
+  fn simulator() -> Simulator {
+    let mut simulator = Simulator::new();
+    simulator.set_master_seed(3);
+    simulator
+      .set_lineup(vec![
+        "random".parse().unwrap(),
+        "greedy".parse().unwrap(),
+        "random".parse().unwrap(),
+      ])
+      .unwrap();
+    let strategies = simulator.get_distinct_strategies();
+    simulator.add_metric(CoinFlowMetric::new(3, strategies));
+    simulator
+  }
+
+  // Sums are added up in another order when accumulators are merged, so floats may differ in the
+  // last bits
+  fn assert_close(left: &Value, right: &Value, path: &str) {
+    match (left, right) {
+      (Value::Number(left), Value::Number(right)) => {
+        let (left, right) = (left.as_f64().unwrap(), right.as_f64().unwrap());
+        assert!(
+          (left - right).abs() <= 1e-9 * left.abs().max(1.0),
+          "{}: {} != {}",
+          path,
+          left,
+          right
+        );
+      }
+      (Value::Array(left), Value::Array(right)) => {
+        assert_eq!(left.len(), right.len(), "{}", path);
+        for (index, (left, right)) in left.iter().zip(right).enumerate() {
+          assert_close(left, right, &format!("{}[{}]", path, index));
         }
-        let filtered_distinct_cards = result.player_cards[player_index]
-          .iter()
-          .filter(|OwnedCard { bought_round, .. }| *bought_round < *round as u8)
-          .map(|OwnedCard { card, .. }| card)
-          .collect::<HashSet<&Card>>();
-        for card in filtered_distinct_cards {
-          *loss_present_card_counts_by_round[index]
-            .1
-            .entry(*card)
-            .or_insert(0) += 1;
+      }
+      (Value::Object(left), Value::Object(right)) => {
+        assert_eq!(
+          left.keys().collect::<Vec<_>>(),
+          right.keys().collect::<Vec<_>>(),
+          "{}",
+          path
+        );
+        for (key, value) in left {
+          assert_close(value, &right[key], &format!("{}.{}", path, key));
         }
       }
+      _ => assert_eq!(left, right, "{}", path),
     }
   }
 
-  pub fn finalize(self, sim_count: usize, num_players: usize) -> SimulationResult {
-    let winner_total_card_counts = self.winner_total_card_counts.into_inner().unwrap();
+  #[test]
+  fn merged_accumulators_match_a_single_fold() {
+    let simulator = simulator();
+    let results = simulator.play_games(60);
 
-    let win_present_card_counts = self.win_present_card_counts.into_inner().unwrap();
-    let mut p_present_win: HashMap<Card, f64> = HashMap::new();
-    for card in Card::iter() {
-      p_present_win.insert(
-        card,
-        *win_present_card_counts.get(&card).unwrap_or(&0) as f64 / sim_count as f64,
-      );
+    let mut single = simulator.new_accumulator(false);
+    for result in &results {
+      single.accumulate(result);
     }
-
-    let loss_present_card_counts = self.loss_present_card_counts.into_inner().unwrap();
-    let mut p_present_loss: HashMap<Card, f64> = HashMap::new();
-    for card in Card::iter() {
-      p_present_loss.insert(
-        card,
-        *loss_present_card_counts.get(&card).unwrap_or(&0) as f64
-          / (sim_count * (num_players - 1)) as f64,
+    let merged = results
+      .chunks(7)
+      .map(|chunk| {
+        let mut accumulator = simulator.new_accumulator(false);
+        for result in chunk {
+          accumulator.accumulate(result);
+        }
+        accumulator
+      })
+      .fold(
+        simulator.new_accumulator(false),
+        SimulationAccumulator::merge,
       );
-    }
 
-    let win_present_card_counts_by_round =
-      self.win_present_card_counts_by_round.into_inner().unwrap();
-    let mut p_present_win_by_round: [(usize, HashMap<Card, f64>); ROUND_BREAKDOWN.len()] =
-      std::array::from_fn(|i| (ROUND_BREAKDOWN[i], HashMap::new()));
-    for (index, round) in ROUND_BREAKDOWN.iter().enumerate() {
-      for card in Card::iter() {
-        p_present_win_by_round[index].1.insert(
-          card,
-          *win_present_card_counts_by_round[index]
-            .1
-            .get(&card)
-            .unwrap_or(&0) as f64
-            / sim_count as f64,
-        );
-      }
-    }
-
-    let loss_present_card_counts_by_round =
-      self.loss_present_card_counts_by_round.into_inner().unwrap();
-    let mut p_present_loss_by_round: [(usize, HashMap<Card, f64>); ROUND_BREAKDOWN.len()] =
-      std::array::from_fn(|i| (ROUND_BREAKDOWN[i], HashMap::new()));
-    for (index, round) in ROUND_BREAKDOWN.iter().enumerate() {
-      for card in Card::iter() {
-        p_present_loss_by_round[index].1.insert(
-          card,
-          *loss_present_card_counts_by_round[index]
-            .1
-            .get(&card)
-            .unwrap_or(&0) as f64
-            / (sim_count * (num_players - 1)) as f64,
-        );
-      }
-    }
-
-    let win_count_by_player_index = self.win_count_by_player_index.into_inner().unwrap();
+    let single = serde_json::to_value(single.finalize()).unwrap();
+    let merged = serde_json::to_value(merged.finalize()).unwrap();
+    assert_eq!(single["game_count"], 60);
+    assert_close(&single, &merged, "result");
+    let parallel = serde_json::to_value(simulator.run(60)).unwrap();
+    assert_close(&single, &parallel, "result");
+  }
 
-    SimulationResult {
-      winner_total_card_counts,
-      p_present_win,
-      p_present_loss,
-      p_present_win_by_round,
-      p_present_loss_by_round,
-      win_count_by_player_index,
+  #[test]
+  fn merging_an_empty_accumulator_changes_nothing() {
+    let simulator = simulator();
+    let mut accumulator = simulator.new_accumulator(true);
+    for result in simulator.play_games(10) {
+      accumulator.accumulate(&result);
     }
+    let expected = serde_json::to_value(accumulator.clone().finalize()).unwrap();
+    let merged = simulator.new_accumulator(true).merge(accumulator);
+    assert_eq!(
+      merged.game_rows.as_ref().map(|rows| rows.seats.len()),
+      Some(30)
+    );
+    assert_close(
+      &expected,
+      &serde_json::to_value(merged.finalize()).unwrap(),
+      "result",
+    );
   }
 }
@@ -0,0 +1,351 @@
+use std::fmt;
+use std::io::Write;
+
+use serde::Serialize;
+
+use crate::game::GameOutcome;
+use crate::simulation::accumulator::SimulationResult;
+use crate::simulation::metrics::CoinFlowRow;
+use crate::simulation::sequential::SequentialResult;
+use crate::simulation::simulator::GameResult;
+use crate::simulation::tournament::TournamentResult;
+use crate::statistics::ConfidenceInterval;
+
+/// Errors of writing simulation results and game rows
+#[derive(Debug)]
+pub enum ExportError {
+  Io(std::io::Error),
+  Json(serde_json::Error),
+  Csv(csv::Error),
+}
+
+impl fmt::Display for ExportError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      ExportError::Io(error) => write!(f, "{}", error),
+      ExportError::Json(error) => write!(f, "{}", error),
+      ExportError::Csv(error) => write!(f, "{}", error),
+    }
+  }
+}
+
+impl std::error::Error for ExportError {}
+
+impl From<std::io::Error> for ExportError {
+  fn from(error: std::io::Error) -> Self {
+    ExportError::Io(error)
+  }
+}
+
+impl From<serde_json::Error> for ExportError {
+  fn from(error: serde_json::Error) -> Self {
+    ExportError::Json(error)
+  }
+}
+
+impl From<csv::Error> for ExportError {
+  fn from(error: csv::Error) -> Self {
+    ExportError::Csv(error)
+  }
+}
+
+/// One statistic of a simulation result in long format, so that every result has the same
+/// columns. Columns that do not apply to a statistic are left empty.
+#[derive(Clone, Serialize)]
+pub struct ResultRow {
+  pub section: String,
+  pub statistic: String,
+  // Card, strategy or draw reason the statistic is about
+  pub key: Option<String>,
+  pub round: Option<usize>,
+  pub seat: Option<usize>,
+  pub value: f64,
+  // Bounds of the 95% confidence interval of rates
+  pub low: Option<f64>,
+  pub high: Option<f64>,
+}
+
+impl ResultRow {
+  pub fn new(section: &str, statistic: &str, value: f64) -> Self {
+    Self {
+      section: section.to_string(),
+      statistic: statistic.to_string(),
+      key: None,
+      round: None,
+      seat: None,
+      value,
+      low: None,
+      high: None,
+    }
+  }
+
+  pub fn key(mut self, key: &str) -> Self {
+    self.key = Some(key.to_string());
+    self
+  }
+
+  pub fn round(mut self, round: usize) -> Self {
+    self.round = Some(round);
+    self
+  }
+
+  pub fn seat(mut self, seat: usize) -> Self {
+    self.seat = Some(seat);
+    self
+  }
+
+  pub fn interval(mut self, interval: &ConfidenceInterval) -> Self {
+    self.low = Some(interval.low);
+    self.high = Some(interval.high);
+    self
+  }
+}
+
+/// One seat of one game. Games have a row for every seat, so the columns do not depend on the
+/// number of players.
+#[derive(Clone, Serialize)]
+pub struct GameRow {
+  pub game: usize,
+  pub seed: u64,
+  pub seat: usize,
+  pub strategy: String,
+  pub placement: usize,
+  pub won: bool,
+  // Win or the draw reason
+  pub outcome: String,
+  pub rounds: usize,
+  pub coins: u16,
+  pub cards: usize,
+  pub landmarks: usize,
+  pub one_die_rolls: usize,
+  pub two_dice_rolls: usize,
+  pub illegal_decisions: usize,
+}
+
+/// Rows of every game of a run, in game order
+#[derive(Clone, Default)]
+pub struct GameRows {
+  pub seats: Vec<GameRow>,
+  // Only of the games played with their events recorded, e.g. with the coin flow metric
+  pub coin_flows: Vec<CoinFlowRow>,
+}
+
+impl GameRows {
+  pub(crate) fn add(&mut self, result: &GameResult, strategies: &[String]) {
+    self.seats.extend(GameRow::from_result(result, strategies));
+    if !result.events.is_empty() {
+      self.coin_flows.extend(CoinFlowRow::from_result(result));
+    }
+  }
+
+  pub(crate) fn extend(&mut self, other: GameRows) {
+    self.seats.extend(other.seats);
+    self.coin_flows.extend(other.coin_flows);
+  }
+
+  pub(crate) fn sort(&mut self) {
+    self.seats.sort_by_key(|row| (row.game, row.seat));
+    // Stable, so the rows of a game stay in source order
+    self.coin_flows.sort_by_key(|row| row.game);
+  }
+}
+
+impl GameRow {
+  /// Rows of every seat of the game. The strategies are the distinct strategies of the lineup
+  /// the game was played with.
+  pub fn from_result(result: &GameResult, strategies: &[String]) -> Vec<GameRow> {
+    let outcome = match result.outcome {
+      GameOutcome::Win(_) => "Win".to_string(),
+      GameOutcome::Draw(reason) => format!("{:?}", reason),
+    };
+    (0..result.player_cards.len())
+      .map(|seat| {
+        let dice_rolls = &result.player_dice_rolls[seat];
+        GameRow {
+          game: result.game_index,
+          seed: result.seed,
+          seat,
+          strategy: strategies[result.seat_strategies[seat]].clone(),
+          placement: result.placements[seat],
+          won: result.outcome.winner() == Some(seat),
+          outcome: outcome.clone(),
+          rounds: result.rounds,
+          coins: result.player_coins[seat],
+          cards: result.player_cards[seat].len(),
+          landmarks: result.player_landmarks[seat].len(),
+          one_die_rolls: dice_rolls.iter().filter(|roll| roll.dice == 1).count(),
+          two_dice_rolls: dice_rolls.iter().filter(|roll| roll.dice == 2).count(),
+          illegal_decisions: result
+            .violations
+            .iter()
+            .filter(|violation| violation.player_index == seat)
+            .count(),
+        }
+      })
+      .collect()
+  }
+}
+
+impl SimulationResult {
+  pub fn to_json(&self) -> Result<String, ExportError> {
+    Ok(serde_json::to_string_pretty(self)?)
+  }
+
+  /// All statistics in long format, section by section, cards in catalog order so that results
+  /// of different runs can be diffed line by line
+  pub fn to_rows(&self) -> Vec<ResultRow> {
+    let mut rows = vec![ResultRow::new(
+      "Simulation",
+      "game_count",
+      self.game_count as f64,
+    )];
+    for section in self.sections.iter() {
+      rows.extend(section.rows.iter().cloned());
+    }
+    rows
+  }
+
+  pub fn write_csv<W: Write>(&self, writer: W) -> Result<(), ExportError> {
+    write_csv(writer, self.to_rows())
+  }
+
+  pub fn save(&self, path: &str) -> Result<(), ExportError> {
+    save_json_or_csv(path, self, || self.to_rows())
+  }
+}
+
+impl SequentialResult {
+  pub fn to_json(&self) -> Result<String, ExportError> {
+    Ok(serde_json::to_string_pretty(self)?)
+  }
+
+  /// Rows of the simulation result, preceded by the outcome of the test
+  pub fn to_rows(&self) -> Vec<ResultRow> {
+    let section = "Sequential test";
+    let mut rows = vec![
+      ResultRow::new(section, "game_count", self.game_count as f64),
+      ResultRow::new(section, "difference", self.difference),
+      ResultRow::new(section, "p_value", self.p_value),
+      ResultRow::new(section, "significant", bool_value(self.significant)),
+    ];
+    rows.extend(self.result.to_rows());
+    rows
+  }
+
+  pub fn write_csv<W: Write>(&self, writer: W) -> Result<(), ExportError> {
+    write_csv(writer, self.to_rows())
+  }
+
+  pub fn save(&self, path: &str) -> Result<(), ExportError> {
+    save_json_or_csv(path, self, || self.to_rows())
+  }
+}
+
+impl TournamentResult {
+  /// Statistics of every standing in long format, in leaderboard order
+  pub fn to_rows(&self) -> Vec<ResultRow> {
+    let section = "Tournament";
+    let mut rows = Vec::new();
+    for standing in self.standings.iter() {
+      let name = standing.strategy.to_string();
+      for (statistic, value) in [
+        ("rating_mu", standing.rating.mu),
+        ("rating_sigma", standing.rating.sigma),
+        ("conservative_rating", standing.rating.conservative()),
+        ("games", standing.games as f64),
+        ("wins", standing.wins as f64),
+        ("draws", standing.draws as f64),
+        ("win_rate", standing.win_rate()),
+        ("average_placement", standing.average_placement()),
+      ] {
+        rows.push(ResultRow::new(section, statistic, value).key(&name));
+      }
+    }
+    rows
+  }
+
+  pub fn save(&self, path: &str) -> Result<(), ExportError> {
+    save_json_or_csv(path, self, || self.to_rows())
+  }
+}
+
+/// Writes rows of every game, of its seats or its coin flow
+pub fn save_game_rows<R: Serialize>(path: &str, rows: &[R]) -> Result<(), ExportError> {
+  save_json_or_csv(path, rows, || rows)
+}
+
+/// Saves the value as pretty JSON, or its rows as CSV if the path ends in .csv. The rows are
+/// only built for CSV.
+pub fn save_json_or_csv<T: Serialize + ?Sized, R: Serialize, I: IntoIterator<Item = R>>(
+  path: &str,
+  value: &T,
+  rows: impl FnOnce() -> I,
+) -> Result<(), ExportError> {
+  if path.ends_with(".csv") {
+    write_csv(std::fs::File::create(path)?, rows())
+  } else {
+    std::fs::write(path, serde_json::to_string_pretty(value)?)?;
+    Ok(())
+  }
+}
+
+fn write_csv<W: Write, R: Serialize>(
+  writer: W,
+  rows: impl IntoIterator<Item = R>,
+) -> Result<(), ExportError> {
+  let mut writer = csv::Writer::from_writer(writer);
+  for row in rows {
+    writer.serialize(row)?;
+  }
+  writer.flush()?;
+  Ok(())
+}
+
+fn bool_value(value: bool) -> f64 {
+  if value {
+    1.0
+  } else {
+    0.0
+  }
+}
+
+/// Every number of a JSON report becomes a row named by its path in the report
+pub(crate) fn flatten_json(
+  section: &str,
+  path: &str,
+  value: &serde_json::Value,
+  rows: &mut Vec<ResultRow>,
+) {
+  let child_path = |key: &str| {
+    if path.is_empty() {
+      key.to_string()
+    } else {
+      format!("{}.{}", path, key)
+    }
+  };
+  match value {
+    serde_json::Value::Number(number) => {
+      let statistic = if path.is_empty() { "value" } else { path };
+      rows.push(ResultRow::new(
+        section,
+        statistic,
+        number.as_f64().unwrap_or(f64::NAN),
+      ));
+    }
+    serde_json::Value::Bool(value) => {
+      let statistic = if path.is_empty() { "value" } else { path };
+      rows.push(ResultRow::new(section, statistic, bool_value(*value)));
+    }
+    serde_json::Value::Array(values) => {
+      for (index, value) in values.iter().enumerate() {
+        flatten_json(section, &child_path(&index.to_string()), value, rows);
+      }
+    }
+    serde_json::Value::Object(values) => {
+      for (key, value) in values.iter() {
+        flatten_json(section, &child_path(key), value, rows);
+      }
+    }
+    serde_json::Value::Null | serde_json::Value::String(_) => {}
+  }
+}
@@ -0,0 +1,129 @@
+use std::any::Any;
+use std::fmt;
+
+use serde::Serialize;
+
+use crate::events::GameEvent;
+use crate::simulation::export::{flatten_json, ResultRow};
+use crate::simulation::simulator::GameResult;
+
+/// A statistic computed over the games of a simulation. Every thread observes its games into its
+/// own copy of the metric, the copies are merged and the merged metric is turned into a report.
+/// Metrics are copied from the one given to the simulator, which must therefore be empty.
+pub trait Metric: Clone + Send + Sync + 'static {
+  /// Serialized into the JSON result, displayed as a section of the text report
+  type Report: Serialize + fmt::Display;
+
+  /// Name of the report section
+  fn name(&self) -> String;
+
+  /// Whether the games must be played with their events recorded for `observe_event`. Events
+  /// slow down the simulation a little.
+  fn wants_events(&self) -> bool {
+    false
+  }
+
+  /// Called with every event of a finished game in the order they happened, before the game
+  /// itself is observed. Only called if the metric wants events.
+  fn observe_event(&mut self, _event: &GameEvent, _game: &GameResult) {}
+
+  fn observe_game(&mut self, game: &GameResult);
+
+  /// Adds the observations of another copy of the metric
+  fn merge(&mut self, other: Self);
+
+  fn finalize(self) -> Self::Report;
+
+  /// Rows of the report in the long CSV format. By default every number of the JSON report
+  /// becomes a row named by its path in the report.
+  fn rows(section: &str, report: &Self::Report) -> Vec<ResultRow> {
+    let mut rows = Vec::new();
+    if let Ok(value) = serde_json::to_value(report) {
+      flatten_json(section, "", &value, &mut rows);
+    }
+    rows
+  }
+}
+
+/// Finalized report of a metric of the simulation
+#[derive(Serialize)]
+pub struct MetricSection {
+  pub name: String,
+  pub report: serde_json::Value,
+  // Text report, shown under the name of the section
+  #[serde(skip)]
+  pub text: String,
+  // Report in the long CSV format
+  #[serde(skip)]
+  pub rows: Vec<ResultRow>,
+}
+
+/// Observes the events of a game, if wanted, and then the game itself
+pub(crate) fn observe<M: Metric>(metric: &mut M, game: &GameResult) {
+  if metric.wants_events() {
+    for event in game.events.iter() {
+      metric.observe_event(event, game);
+    }
+  }
+  metric.observe_game(game);
+}
+
+/// Object safe form of `Metric`, so that metrics of any type can be kept in one list
+pub(crate) trait DynMetric: Send + Sync {
+  fn wants_events(&self) -> bool;
+  fn observe(&mut self, game: &GameResult);
+  fn merge_boxed(&mut self, other: Box<dyn DynMetric>);
+  fn clone_boxed(&self) -> Box<dyn DynMetric>;
+  fn finalize_boxed(self: Box<Self>) -> MetricSection;
+  fn into_any(self: Box<Self>) -> Box<dyn Any>;
+  fn as_any(&self) -> &dyn Any;
+}
+
+impl<M: Metric> DynMetric for M {
+  fn wants_events(&self) -> bool {
+    Metric::wants_events(self)
+  }
+
+  fn observe(&mut self, game: &GameResult) {
+    observe(self, game);
+  }
+
+  fn merge_boxed(&mut self, other: Box<dyn DynMetric>) {
+    // Copies of a metric are always merged in the same position, so they have the same type
+    let other = other
+      .into_any()
+      .downcast::<M>()
+      .expect("Merged metrics of different types");
+    self.merge(*other);
+  }
+
+  fn clone_boxed(&self) -> Box<dyn DynMetric> {
+    Box::new(self.clone())
+  }
+
+  fn finalize_boxed(self: Box<Self>) -> MetricSection {
+    let name = self.name();
+    let report = (*self).finalize();
+    let rows = M::rows(&name, &report);
+    MetricSection {
+      name,
+      report: serde_json::to_value(&report).unwrap_or(serde_json::Value::Null),
+      text: report.to_string(),
+      rows,
+    }
+  }
+
+  fn into_any(self: Box<Self>) -> Box<dyn Any> {
+    self
+  }
+
+  fn as_any(&self) -> &dyn Any {
+    self
+  }
+}
+
+impl Clone for Box<dyn DynMetric> {
+  fn clone(&self) -> Self {
+    self.clone_boxed()
+  }
+}
@@ -0,0 +1,247 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+use serde::Serialize;
+use strum::IntoEnumIterator;
+
+use crate::game::GameOutcome;
+use crate::models::player::OwnedCard;
+use crate::models::Card;
+use crate::simulation::export::ResultRow;
+use crate::simulation::metric::Metric;
+use crate::simulation::simulator::GameResult;
+use crate::statistics::{wilson_interval, ConfidenceInterval};
+
+/// Sums over the owned copies of a card
+#[derive(Clone, Copy, Default)]
+struct IncomeSums {
+  copies: usize,
+  income: usize,
+  paid_back: usize,
+  // Rounds from buying to paying back, of the copies that paid back
+  payback_rounds: usize,
+}
+
+impl IncomeSums {
+  fn add(&mut self, owned: &OwnedCard) {
+    self.copies += 1;
+    self.income += owned.income as usize;
+    if let Some(round) = owned.paid_back_round {
+      self.paid_back += 1;
+      self.payback_rounds += round.saturating_sub(owned.bought_round);
+    }
+  }
+
+  fn merge(&mut self, other: &IncomeSums) {
+    self.copies += other.copies;
+    self.income += other.income;
+    self.paid_back += other.paid_back;
+    self.payback_rounds += other.payback_rounds;
+  }
+}
+
+/// Coins earned by every copy of a card over the game, of the winner compared to the other
+/// players, over the games that had a winner. A card that changed hands counts as a copy of every
+/// player that owned it, with the income it earned them.
+#[derive(Clone)]
+pub struct CardIncomeMetric {
+  win_income: HashMap<Card, IncomeSums>,
+  loss_income: HashMap<Card, IncomeSums>,
+}
+
+/// Return on the cost of the copies of a card
+#[derive(Serialize)]
+pub struct CardReturn {
+  pub copies: usize,
+  // Average coins earned by a copy, null if the card was never owned
+  pub average_return: Option<f64>,
+  // Coins earned per coin spent on the card
+  pub return_per_cost: Option<f64>,
+  // Share of the copies whose income covered their cost
+  pub paid_back_rate: Option<f64>,
+  pub paid_back_interval: ConfidenceInterval,
+  // Average rounds from buying a copy to paying it back, of the copies that paid back
+  pub average_payback_rounds: Option<f64>,
+}
+
+#[derive(Serialize)]
+pub struct CardIncomeReport {
+  pub card_return_win: BTreeMap<Card, CardReturn>,
+  pub card_return_loss: BTreeMap<Card, CardReturn>,
+}
+
+impl CardIncomeMetric {
+  pub fn new() -> Self {
+    Self {
+      win_income: HashMap::new(),
+      loss_income: HashMap::new(),
+    }
+  }
+}
+
+impl Default for CardIncomeMetric {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl CardReturn {
+  fn new(card: Card, sums: IncomeSums) -> Self {
+    let ratio = |numerator: usize, denominator: usize| {
+      (denominator > 0).then(|| numerator as f64 / denominator as f64)
+    };
+    CardReturn {
+      copies: sums.copies,
+      average_return: ratio(sums.income, sums.copies),
+      return_per_cost: ratio(sums.income, sums.copies * card.def().cost as usize),
+      paid_back_rate: ratio(sums.paid_back, sums.copies),
+      paid_back_interval: wilson_interval(sums.paid_back, sums.copies),
+      average_payback_rounds: ratio(sums.payback_rounds, sums.paid_back),
+    }
+  }
+}
+
+impl Metric for CardIncomeMetric {
+  type Report = CardIncomeReport;
+
+  fn name(&self) -> String {
+    "Card income".to_string()
+  }
+
+  fn observe_game(&mut self, game: &GameResult) {
+    let GameOutcome::Win(winner_index) = game.outcome else {
+      return;
+    };
+    for (player_index, cards) in game.player_cards.iter().enumerate() {
+      let income = if player_index == winner_index {
+        &mut self.win_income
+      } else {
+        &mut self.loss_income
+      };
+      for owned in cards
+        .iter()
+        .chain(game.player_retired_cards[player_index].iter())
+      {
+        income.entry(owned.card).or_default().add(owned);
+      }
+    }
+  }
+
+  fn merge(&mut self, other: Self) {
+    for (income, other_income) in [
+      (&mut self.win_income, other.win_income),
+      (&mut self.loss_income, other.loss_income),
+    ] {
+      for (card, sums) in other_income {
+        income.entry(card).or_default().merge(&sums);
+      }
+    }
+  }
+
+  fn finalize(self) -> CardIncomeReport {
+    let returns = |income: &HashMap<Card, IncomeSums>| -> BTreeMap<Card, CardReturn> {
+      Card::iter()
+        .map(|card| {
+          let sums = income.get(&card).copied().unwrap_or_default();
+          (card, CardReturn::new(card, sums))
+        })
+        .collect()
+    };
+    CardIncomeReport {
+      card_return_win: returns(&self.win_income),
+      card_return_loss: returns(&self.loss_income),
+    }
+  }
+
+  fn rows(section: &str, report: &CardIncomeReport) -> Vec<ResultRow> {
+    let mut rows = Vec::new();
+    for card in Card::iter() {
+      let name = card.def().name;
+      for (outcome, returns) in [
+        ("win", &report.card_return_win[&card]),
+        ("loss", &report.card_return_loss[&card]),
+      ] {
+        rows.push(
+          ResultRow::new(
+            section,
+            &format!("copies_{}", outcome),
+            returns.copies as f64,
+          )
+          .key(name),
+        );
+        // Returns of cards that were never owned are left out
+        for (statistic, value) in [
+          ("average_return", returns.average_return),
+          ("return_per_cost", returns.return_per_cost),
+          ("average_payback_rounds", returns.average_payback_rounds),
+        ] {
+          if let Some(value) = value {
+            rows.push(
+              ResultRow::new(section, &format!("{}_{}", statistic, outcome), value).key(name),
+            );
+          }
+        }
+        if let Some(rate) = returns.paid_back_rate {
+          rows.push(
+            ResultRow::new(section, &format!("paid_back_rate_{}", outcome), rate)
+              .key(name)
+              .interval(&returns.paid_back_interval),
+          );
+        }
+      }
+    }
+    rows
+  }
+}
+
+impl fmt::Display for CardIncomeReport {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let format_value = |value: Option<f64>, precision: usize| match value {
+      Some(value) => format!("{:.*}", precision, value),
+      None => "-".to_string(),
+    };
+    // Sorted by the return per cost of the winner, best first
+    let return_per_cost = |card: &Card| {
+      self.card_return_win[card]
+        .return_per_cost
+        .unwrap_or(f64::NEG_INFINITY)
+    };
+    let mut cards = Card::iter().collect::<Vec<_>>();
+    cards.sort_by(|a, b| return_per_cost(b).total_cmp(&return_per_cost(a)));
+
+    writeln!(
+      f,
+      "{:<20} {:>14} {:>14} {:>14} {:>14}",
+      "Card", "Return W/L", "Per cost W/L", "Paid back W/L", "Payback W/L"
+    )?;
+    for card in cards {
+      let (win, loss) = (&self.card_return_win[&card], &self.card_return_loss[&card]);
+      writeln!(
+        f,
+        "{:<20} {:>14} {:>14} {:>14} {:>14}",
+        card.def().name,
+        format!(
+          "{}/{}",
+          format_value(win.average_return, 1),
+          format_value(loss.average_return, 1)
+        ),
+        format!(
+          "{}/{}",
+          format_value(win.return_per_cost, 2),
+          format_value(loss.return_per_cost, 2)
+        ),
+        format!(
+          "{}/{}",
+          format_value(win.paid_back_rate, 2),
+          format_value(loss.paid_back_rate, 2)
+        ),
+        format!(
+          "{}/{}",
+          format_value(win.average_payback_rounds, 1),
+          format_value(loss.average_payback_rounds, 1)
+        )
+      )?;
+    }
+    Ok(())
+  }
+}
@@ -0,0 +1,224 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+
+use colored::Colorize;
+use serde::Serialize;
+use strum::IntoEnumIterator;
+
+use crate::game::GameOutcome;
+use crate::models::player::OwnedCard;
+use crate::models::Card;
+use crate::simulation::export::ResultRow;
+use crate::simulation::metric::Metric;
+use crate::simulation::metrics::merge_counts;
+use crate::simulation::simulator::GameResult;
+use crate::statistics::{
+  format_p_value, two_proportion_p_value, wilson_interval, ConfidenceInterval,
+};
+
+/// How often each card is owned by the winner compared to the other players, over the games that
+/// had a winner
+#[derive(Clone)]
+pub struct CardPresenceMetric {
+  num_players: usize,
+  games_with_winner: usize,
+  // Total card counts for the winner HashMap<Card, count>
+  winner_total_card_counts: HashMap<Card, usize>,
+  // Present card counts for the winner HashMap<Card, count>
+  win_present_card_counts: HashMap<Card, usize>,
+  // Present card counts for the other players HashMap<Card, count>
+  loss_present_card_counts: HashMap<Card, usize>,
+}
+
+#[derive(Serialize)]
+pub struct CardPresenceReport {
+  pub winner_total_card_counts: BTreeMap<Card, usize>,
+  pub p_present_win: BTreeMap<Card, f64>,
+  pub p_present_loss: BTreeMap<Card, f64>,
+  pub p_present_win_interval: BTreeMap<Card, ConfidenceInterval>,
+  pub p_present_loss_interval: BTreeMap<Card, ConfidenceInterval>,
+  // p-value of the test that a card is as often present for the winner as for the other players
+  pub presence_p_value: BTreeMap<Card, f64>,
+}
+
+impl CardPresenceMetric {
+  pub fn new(num_players: usize) -> Self {
+    Self {
+      num_players,
+      games_with_winner: 0,
+      winner_total_card_counts: HashMap::new(),
+      win_present_card_counts: HashMap::new(),
+      loss_present_card_counts: HashMap::new(),
+    }
+  }
+
+  /// Games with a winner and how often the card was present for the winner
+  pub fn win_presence(&self, card: &Card) -> (usize, usize) {
+    (
+      *self.win_present_card_counts.get(card).unwrap_or(&0),
+      self.games_with_winner,
+    )
+  }
+
+  /// Players that did not win and how often the card was present for them
+  pub fn loss_presence(&self, card: &Card) -> (usize, usize) {
+    (
+      *self.loss_present_card_counts.get(card).unwrap_or(&0),
+      self.games_with_winner * (self.num_players - 1),
+    )
+  }
+}
+
+impl Metric for CardPresenceMetric {
+  type Report = CardPresenceReport;
+
+  fn name(&self) -> String {
+    "Card presence".to_string()
+  }
+
+  fn observe_game(&mut self, game: &GameResult) {
+    // Card statistics only make sense for games with a winner
+    let GameOutcome::Win(winner_index) = game.outcome else {
+      return;
+    };
+    self.games_with_winner += 1;
+
+    for OwnedCard { card, .. } in game.player_cards[winner_index].iter() {
+      *self.winner_total_card_counts.entry(*card).or_insert(0) += 1;
+    }
+    for (player_index, cards) in game.player_cards.iter().enumerate() {
+      let presence = if player_index == winner_index {
+        &mut self.win_present_card_counts
+      } else {
+        &mut self.loss_present_card_counts
+      };
+      let distinct_cards = cards
+        .iter()
+        .map(|OwnedCard { card, .. }| card)
+        .collect::<HashSet<&Card>>();
+      for card in distinct_cards {
+        *presence.entry(*card).or_insert(0) += 1;
+      }
+    }
+  }
+
+  fn merge(&mut self, other: Self) {
+    self.games_with_winner += other.games_with_winner;
+    merge_counts(
+      &mut self.winner_total_card_counts,
+      other.winner_total_card_counts,
+    );
+    merge_counts(
+      &mut self.win_present_card_counts,
+      other.win_present_card_counts,
+    );
+    merge_counts(
+      &mut self.loss_present_card_counts,
+      other.loss_present_card_counts,
+    );
+  }
+
+  fn finalize(self) -> CardPresenceReport {
+    let wins = self.games_with_winner.max(1);
+    let losses = wins * (self.num_players - 1);
+    let mut report = CardPresenceReport {
+      winner_total_card_counts: BTreeMap::new(),
+      p_present_win: BTreeMap::new(),
+      p_present_loss: BTreeMap::new(),
+      p_present_win_interval: BTreeMap::new(),
+      p_present_loss_interval: BTreeMap::new(),
+      presence_p_value: BTreeMap::new(),
+    };
+    for card in Card::iter() {
+      let win_present = *self.win_present_card_counts.get(&card).unwrap_or(&0);
+      let loss_present = *self.loss_present_card_counts.get(&card).unwrap_or(&0);
+      report
+        .p_present_win
+        .insert(card, win_present as f64 / wins as f64);
+      report
+        .p_present_loss
+        .insert(card, loss_present as f64 / losses as f64);
+      report
+        .p_present_win_interval
+        .insert(card, wilson_interval(win_present, wins));
+      report
+        .p_present_loss_interval
+        .insert(card, wilson_interval(loss_present, losses));
+      report.presence_p_value.insert(
+        card,
+        two_proportion_p_value(win_present, wins, loss_present, losses),
+      );
+    }
+    report.winner_total_card_counts = self.winner_total_card_counts.into_iter().collect();
+    report
+  }
+
+  fn rows(section: &str, report: &CardPresenceReport) -> Vec<ResultRow> {
+    let mut rows = Vec::new();
+    for card in Card::iter() {
+      let name = card.def().name;
+      rows.push(
+        ResultRow::new(section, "p_present_win", report.p_present_win[&card])
+          .key(name)
+          .interval(&report.p_present_win_interval[&card]),
+      );
+      rows.push(
+        ResultRow::new(section, "p_present_loss", report.p_present_loss[&card])
+          .key(name)
+          .interval(&report.p_present_loss_interval[&card]),
+      );
+      rows.push(
+        ResultRow::new(section, "presence_p_value", report.presence_p_value[&card]).key(name),
+      );
+      let total = *report.winner_total_card_counts.get(&card).unwrap_or(&0);
+      rows.push(ResultRow::new(section, "winner_total_card_count", total as f64).key(name));
+    }
+    rows
+  }
+}
+
+impl CardPresenceReport {
+  /// Cards sorted by how much more often the winner owns them, best first
+  pub fn cards_by_difference(&self) -> Vec<Card> {
+    let mut cards = Card::iter().collect::<Vec<_>>();
+    cards.sort_by(|a, b| {
+      let diff_a = self.p_present_win[a] - self.p_present_loss[a];
+      let diff_b = self.p_present_win[b] - self.p_present_loss[b];
+      diff_b.total_cmp(&diff_a)
+    });
+    cards
+  }
+}
+
+impl fmt::Display for CardPresenceReport {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    writeln!(
+      f,
+      "{:<20} {:>8} {:>13} {:>8} {:>13} {:>8} {:>8} {:>8}",
+      "Card", "Win", "95% CI", "Loss", "95% CI", "Diff", "p", "Total"
+    )?;
+    for card in self.cards_by_difference() {
+      let p_win = self.p_present_win[&card];
+      let p_loss = self.p_present_loss[&card];
+      let diff = format!("{:+.3}", p_win - p_loss);
+      let diff = if p_win >= p_loss {
+        diff.green()
+      } else {
+        diff.red()
+      };
+      writeln!(
+        f,
+        "{:<20} {:>8.3} {:>13} {:>8.3} {:>13} {:>8} {:>8} {:>8}",
+        card.def().name,
+        p_win,
+        self.p_present_win_interval[&card],
+        p_loss,
+        self.p_present_loss_interval[&card],
+        diff,
+        format_p_value(self.presence_p_value[&card]),
+        self.winner_total_card_counts.get(&card).unwrap_or(&0)
+      )?;
+    }
+    Ok(())
+  }
+}
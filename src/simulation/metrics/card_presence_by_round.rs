@@ -0,0 +1,206 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+
+use serde::Serialize;
+use strum::IntoEnumIterator;
+
+use crate::game::GameOutcome;
+use crate::models::player::OwnedCard;
+use crate::models::Card;
+use crate::simulation::export::ResultRow;
+use crate::simulation::metric::Metric;
+use crate::simulation::metrics::merge_counts;
+use crate::simulation::simulator::GameResult;
+use crate::statistics::{wilson_interval, ConfidenceInterval};
+
+const ROUND_BREAKDOWN: [usize; 3] = [5, 10, 15];
+
+/// Card presence of the winner and the other players counting only the cards bought before
+/// each of the breakdown rounds, over the games that had a winner
+#[derive(Clone)]
+pub struct CardPresenceByRoundMetric {
+  num_players: usize,
+  games_with_winner: usize,
+  // Present card counts for the winner by round (round number, HashMap<Card, count>)
+  win_present_card_counts_by_round: [(usize, HashMap<Card, usize>); ROUND_BREAKDOWN.len()],
+  // Present card counts for the other players by round (round number, HashMap<Card, count>)
+  loss_present_card_counts_by_round: [(usize, HashMap<Card, usize>); ROUND_BREAKDOWN.len()],
+}
+
+#[derive(Serialize)]
+pub struct CardPresenceByRoundReport {
+  pub p_present_win_by_round: [(usize, BTreeMap<Card, f64>); ROUND_BREAKDOWN.len()],
+  pub p_present_loss_by_round: [(usize, BTreeMap<Card, f64>); ROUND_BREAKDOWN.len()],
+  pub p_present_win_by_round_interval:
+    [(usize, BTreeMap<Card, ConfidenceInterval>); ROUND_BREAKDOWN.len()],
+  pub p_present_loss_by_round_interval:
+    [(usize, BTreeMap<Card, ConfidenceInterval>); ROUND_BREAKDOWN.len()],
+}
+
+impl CardPresenceByRoundMetric {
+  pub fn new(num_players: usize) -> Self {
+    Self {
+      num_players,
+      games_with_winner: 0,
+      win_present_card_counts_by_round: std::array::from_fn(|i| {
+        (ROUND_BREAKDOWN[i], HashMap::new())
+      }),
+      loss_present_card_counts_by_round: std::array::from_fn(|i| {
+        (ROUND_BREAKDOWN[i], HashMap::new())
+      }),
+    }
+  }
+}
+
+impl Metric for CardPresenceByRoundMetric {
+  type Report = CardPresenceByRoundReport;
+
+  fn name(&self) -> String {
+    "Card presence by round".to_string()
+  }
+
+  fn observe_game(&mut self, game: &GameResult) {
+    let GameOutcome::Win(winner_index) = game.outcome else {
+      return;
+    };
+    self.games_with_winner += 1;
+
+    for (index, round) in ROUND_BREAKDOWN.iter().enumerate() {
+      for (player_index, cards) in game.player_cards.iter().enumerate() {
+        let presence = if player_index == winner_index {
+          &mut self.win_present_card_counts_by_round[index].1
+        } else {
+          &mut self.loss_present_card_counts_by_round[index].1
+        };
+        let filtered_distinct_cards = cards
+          .iter()
+          .filter(|OwnedCard { bought_round, .. }| *bought_round < *round)
+          .map(|OwnedCard { card, .. }| card)
+          .collect::<HashSet<&Card>>();
+        for card in filtered_distinct_cards {
+          *presence.entry(*card).or_insert(0) += 1;
+        }
+      }
+    }
+  }
+
+  fn merge(&mut self, other: Self) {
+    self.games_with_winner += other.games_with_winner;
+    for (counts, (_, other_counts)) in self
+      .win_present_card_counts_by_round
+      .iter_mut()
+      .zip(other.win_present_card_counts_by_round)
+    {
+      merge_counts(&mut counts.1, other_counts);
+    }
+    for (counts, (_, other_counts)) in self
+      .loss_present_card_counts_by_round
+      .iter_mut()
+      .zip(other.loss_present_card_counts_by_round)
+    {
+      merge_counts(&mut counts.1, other_counts);
+    }
+  }
+
+  fn finalize(self) -> CardPresenceByRoundReport {
+    let wins = self.games_with_winner.max(1);
+    let losses = wins * (self.num_players - 1);
+    let rates = |counts: &HashMap<Card, usize>, trials: usize| -> BTreeMap<Card, f64> {
+      Card::iter()
+        .map(|card| {
+          (
+            card,
+            *counts.get(&card).unwrap_or(&0) as f64 / trials as f64,
+          )
+        })
+        .collect()
+    };
+    let intervals =
+      |counts: &HashMap<Card, usize>, trials: usize| -> BTreeMap<Card, ConfidenceInterval> {
+        Card::iter()
+          .map(|card| {
+            (
+              card,
+              wilson_interval(*counts.get(&card).unwrap_or(&0), trials),
+            )
+          })
+          .collect()
+      };
+
+    let win_counts = &self.win_present_card_counts_by_round;
+    let loss_counts = &self.loss_present_card_counts_by_round;
+    CardPresenceByRoundReport {
+      p_present_win_by_round: std::array::from_fn(|i| {
+        (ROUND_BREAKDOWN[i], rates(&win_counts[i].1, wins))
+      }),
+      p_present_loss_by_round: std::array::from_fn(|i| {
+        (ROUND_BREAKDOWN[i], rates(&loss_counts[i].1, losses))
+      }),
+      p_present_win_by_round_interval: std::array::from_fn(|i| {
+        (ROUND_BREAKDOWN[i], intervals(&win_counts[i].1, wins))
+      }),
+      p_present_loss_by_round_interval: std::array::from_fn(|i| {
+        (ROUND_BREAKDOWN[i], intervals(&loss_counts[i].1, losses))
+      }),
+    }
+  }
+
+  fn rows(section: &str, report: &CardPresenceByRoundReport) -> Vec<ResultRow> {
+    let mut rows = Vec::new();
+    for index in 0..report.p_present_win_by_round.len() {
+      let (round, win) = &report.p_present_win_by_round[index];
+      let (_, loss) = &report.p_present_loss_by_round[index];
+      let (_, win_interval) = &report.p_present_win_by_round_interval[index];
+      let (_, loss_interval) = &report.p_present_loss_by_round_interval[index];
+      for card in Card::iter() {
+        let name = card.def().name;
+        rows.push(
+          ResultRow::new(section, "p_present_win", win[&card])
+            .key(name)
+            .round(*round)
+            .interval(&win_interval[&card]),
+        );
+        rows.push(
+          ResultRow::new(section, "p_present_loss", loss[&card])
+            .key(name)
+            .round(*round)
+            .interval(&loss_interval[&card]),
+        );
+      }
+    }
+    rows
+  }
+}
+
+impl fmt::Display for CardPresenceByRoundReport {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    // Sorted by the difference in the last breakdown round, best first
+    let last = ROUND_BREAKDOWN.len() - 1;
+    let (win, loss) = (
+      &self.p_present_win_by_round[last].1,
+      &self.p_present_loss_by_round[last].1,
+    );
+    let mut cards = Card::iter().collect::<Vec<_>>();
+    cards.sort_by(|a, b| (win[b] - loss[b]).total_cmp(&(win[a] - loss[a])));
+
+    write!(f, "{:<20}", "Card")?;
+    for (round, _) in self.p_present_win_by_round.iter() {
+      write!(f, " {:>10}", format!("<R{} W/L", round))?;
+    }
+    writeln!(f)?;
+    for card in cards {
+      write!(f, "{:<20}", card.def().name)?;
+      for index in 0..ROUND_BREAKDOWN.len() {
+        let by_round_win = self.p_present_win_by_round[index].1[&card];
+        let by_round_loss = self.p_present_loss_by_round[index].1[&card];
+        write!(
+          f,
+          " {:>10}",
+          format!("{:.2}/{:.2}", by_round_win, by_round_loss)
+        )?;
+      }
+      writeln!(f)?;
+    }
+    Ok(())
+  }
+}
@@ -0,0 +1,415 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use serde::Serialize;
+
+use crate::events::GameEvent;
+use crate::simulation::metric::Metric;
+use crate::simulation::simulator::GameResult;
+
+/// Coins moved by every source card or landmark in one game or summed over many, between
+/// players [from][to] and from the bank [to]. Coins paid to the bank (Park) count as negative
+/// bank income. Players are seats or strategies.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct CoinFlow {
+  pub transfers: BTreeMap<String, Vec<Vec<f64>>>,
+  pub bank_income: BTreeMap<String, Vec<f64>>,
+}
+
+impl CoinFlow {
+  /// Coin flow between the seats of a game, from its events. The game must have been played
+  /// with its events recorded.
+  pub fn of_game(game: &GameResult) -> Self {
+    let num_players = game.player_coins.len();
+    let mut flow = CoinFlow::default();
+    for event in game.events.iter() {
+      match event {
+        GameEvent::CoinTransfer {
+          from_index,
+          to_index,
+          coins,
+          source,
+        } => {
+          flow.transfers_of(source.name(), num_players)[*from_index][*to_index] += *coins as f64;
+        }
+        GameEvent::BankTransfer {
+          player_index,
+          coins,
+          source,
+        } => {
+          flow.bank_income_of(source.name(), num_players)[*player_index] += *coins as f64;
+        }
+        _ => {}
+      }
+    }
+    flow
+  }
+
+  fn transfers_of(&mut self, source: &str, num_players: usize) -> &mut Vec<Vec<f64>> {
+    self
+      .transfers
+      .entry(source.to_string())
+      .or_insert_with(|| vec![vec![0.0; num_players]; num_players])
+  }
+
+  fn bank_income_of(&mut self, source: &str, num_players: usize) -> &mut Vec<f64> {
+    self
+      .bank_income
+      .entry(source.to_string())
+      .or_insert_with(|| vec![0.0; num_players])
+  }
+
+  /// Adds another flow, with its players mapped to the players of this flow
+  fn add(&mut self, other: &CoinFlow, num_players: usize, player_of: impl Fn(usize) -> usize) {
+    for (source, transfers) in other.transfers.iter() {
+      let own = self.transfers_of(source, num_players);
+      for (from, row) in transfers.iter().enumerate() {
+        for (to, coins) in row.iter().enumerate() {
+          own[player_of(from)][player_of(to)] += coins;
+        }
+      }
+    }
+    for (source, income) in other.bank_income.iter() {
+      let own = self.bank_income_of(source, num_players);
+      for (to, coins) in income.iter().enumerate() {
+        own[player_of(to)] += coins;
+      }
+    }
+  }
+
+  fn scaled(mut self, factor: f64) -> Self {
+    for transfers in self.transfers.values_mut() {
+      for coins in transfers.iter_mut().flatten() {
+        *coins *= factor;
+      }
+    }
+    for income in self.bank_income.values_mut() {
+      for coins in income.iter_mut() {
+        *coins *= factor;
+      }
+    }
+    self
+  }
+
+  /// Coins moved between players over all sources [from][to]
+  pub fn total_transfers(&self, num_players: usize) -> Vec<Vec<f64>> {
+    let mut total = vec![vec![0.0; num_players]; num_players];
+    for transfers in self.transfers.values() {
+      for (from, row) in transfers.iter().enumerate() {
+        for (to, coins) in row.iter().enumerate() {
+          total[from][to] += coins;
+        }
+      }
+    }
+    total
+  }
+
+  /// Coins from the bank over all sources [to]
+  pub fn total_bank_income(&self, num_players: usize) -> Vec<f64> {
+    let mut total = vec![0.0; num_players];
+    for income in self.bank_income.values() {
+      for (to, coins) in income.iter().enumerate() {
+        total[to] += coins;
+      }
+    }
+    total
+  }
+}
+
+/// Coins moved by one source in one game from one seat, or the bank, to another. Rows only
+/// exist for the pairs that moved coins.
+#[derive(Clone, Serialize)]
+pub struct CoinFlowRow {
+  pub game: usize,
+  pub seed: u64,
+  pub source: String,
+  // Null (an empty cell in CSV) for the bank
+  pub from_seat: Option<usize>,
+  pub to_seat: usize,
+  // Negative for coins paid to the bank
+  pub coins: f64,
+}
+
+impl CoinFlowRow {
+  /// Rows of the coin flow of a game, by source. The game must have been played with its events
+  /// recorded.
+  pub fn from_result(result: &GameResult) -> Vec<CoinFlowRow> {
+    let flow = CoinFlow::of_game(result);
+    let mut sources = flow
+      .transfers
+      .keys()
+      .chain(flow.bank_income.keys())
+      .collect::<Vec<_>>();
+    sources.sort();
+    sources.dedup();
+    let row = |source: &str, from_seat: Option<usize>, to_seat: usize, coins: f64| CoinFlowRow {
+      game: result.game_index,
+      seed: result.seed,
+      source: source.to_string(),
+      from_seat,
+      to_seat,
+      coins,
+    };
+    let mut rows = Vec::new();
+    for source in sources {
+      if let Some(income) = flow.bank_income.get(source) {
+        for (to, coins) in income
+          .iter()
+          .enumerate()
+          .filter(|(_, coins)| **coins != 0.0)
+        {
+          rows.push(row(source, None, to, *coins));
+        }
+      }
+      if let Some(transfers) = flow.transfers.get(source) {
+        for (from, transfers_from) in transfers.iter().enumerate() {
+          for (to, coins) in transfers_from.iter().enumerate() {
+            if *coins != 0.0 {
+              rows.push(row(source, Some(from), to, *coins));
+            }
+          }
+        }
+      }
+    }
+    rows
+  }
+}
+
+/// Coins moved between players and from the bank, by source card or landmark, between seats and
+/// between strategies. Also compares the coins the winner took from the other players to the
+/// coins the other players took. Needs the events of every game.
+#[derive(Clone)]
+pub struct CoinFlowMetric {
+  num_players: usize,
+  // Names of the distinct strategies, GameResult::seat_strategies indexes into this
+  strategies: Vec<String>,
+  game_count: usize,
+  by_seat: CoinFlow,
+  by_strategy: CoinFlow,
+  games_with_winner: usize,
+  // Coins taken from other players minus coins lost to them, summed over the games with a winner
+  winner_net_transfers: f64,
+  loser_net_transfers: f64,
+  winner_bank_income: f64,
+  loser_bank_income: f64,
+}
+
+/// Coin flows averaged per game
+#[derive(Serialize)]
+pub struct CoinFlowReport {
+  pub game_count: usize,
+  pub num_players: usize,
+  pub strategies: Vec<String>,
+  pub by_seat: CoinFlow,
+  pub by_strategy: CoinFlow,
+  // Averages per player per game with a winner, null without such games
+  pub winner_net_transfers: Option<f64>,
+  pub loser_net_transfers: Option<f64>,
+  pub winner_bank_income: Option<f64>,
+  pub loser_bank_income: Option<f64>,
+}
+
+impl CoinFlowMetric {
+  /// The strategies are the distinct strategies of the lineup, as given by
+  /// `Simulator::get_distinct_strategies`
+  pub fn new(num_players: usize, strategies: Vec<String>) -> Self {
+    Self {
+      num_players,
+      strategies,
+      game_count: 0,
+      by_seat: CoinFlow::default(),
+      by_strategy: CoinFlow::default(),
+      games_with_winner: 0,
+      winner_net_transfers: 0.0,
+      loser_net_transfers: 0.0,
+      winner_bank_income: 0.0,
+      loser_bank_income: 0.0,
+    }
+  }
+}
+
+impl Metric for CoinFlowMetric {
+  type Report = CoinFlowReport;
+
+  fn name(&self) -> String {
+    "Coin flow".to_string()
+  }
+
+  fn wants_events(&self) -> bool {
+    true
+  }
+
+  fn observe_game(&mut self, game: &GameResult) {
+    let flow = CoinFlow::of_game(game);
+    self.game_count += 1;
+    self.by_seat.add(&flow, self.num_players, |seat| seat);
+    self.by_strategy.add(&flow, self.strategies.len(), |seat| {
+      game.seat_strategies[seat]
+    });
+
+    let Some(winner_index) = game.outcome.winner() else {
+      return;
+    };
+    self.games_with_winner += 1;
+    let transfers = flow.total_transfers(self.num_players);
+    let bank_income = flow.total_bank_income(self.num_players);
+    for seat in 0..self.num_players {
+      let taken: f64 = (0..self.num_players)
+        .map(|from| transfers[from][seat])
+        .sum();
+      let lost: f64 = transfers[seat].iter().sum();
+      if seat == winner_index {
+        self.winner_net_transfers += taken - lost;
+        self.winner_bank_income += bank_income[seat];
+      } else {
+        self.loser_net_transfers += taken - lost;
+        self.loser_bank_income += bank_income[seat];
+      }
+    }
+  }
+
+  fn merge(&mut self, other: Self) {
+    self.game_count += other.game_count;
+    self
+      .by_seat
+      .add(&other.by_seat, self.num_players, |seat| seat);
+    self
+      .by_strategy
+      .add(&other.by_strategy, self.strategies.len(), |strategy| {
+        strategy
+      });
+    self.games_with_winner += other.games_with_winner;
+    self.winner_net_transfers += other.winner_net_transfers;
+    self.loser_net_transfers += other.loser_net_transfers;
+    self.winner_bank_income += other.winner_bank_income;
+    self.loser_bank_income += other.loser_bank_income;
+  }
+
+  fn finalize(self) -> CoinFlowReport {
+    let per_game = 1.0 / self.game_count.max(1) as f64;
+    let winners = self.games_with_winner;
+    let losers = winners * (self.num_players - 1);
+    let average = |sum: f64, players: usize| (players > 0).then(|| sum / players as f64);
+    CoinFlowReport {
+      game_count: self.game_count,
+      num_players: self.num_players,
+      strategies: self.strategies,
+      by_seat: self.by_seat.scaled(per_game),
+      by_strategy: self.by_strategy.scaled(per_game),
+      winner_net_transfers: average(self.winner_net_transfers, winners),
+      loser_net_transfers: average(self.loser_net_transfers, losers),
+      winner_bank_income: average(self.winner_bank_income, winners),
+      loser_bank_income: average(self.loser_bank_income, losers),
+    }
+  }
+}
+
+/// Writes a [from][to] matrix with the bank as the last row
+fn write_matrix(
+  f: &mut fmt::Formatter<'_>,
+  names: &[String],
+  transfers: &[Vec<f64>],
+  bank_income: &[f64],
+) -> fmt::Result {
+  write!(f, "{:<24}", "From \\ To")?;
+  for name in names {
+    write!(f, " {:>10}", name)?;
+  }
+  writeln!(f)?;
+  for (from, row) in transfers.iter().enumerate() {
+    write!(f, "{:<24}", names[from])?;
+    for coins in row {
+      write!(f, " {:>10.2}", coins)?;
+    }
+    writeln!(f)?;
+  }
+  write!(f, "{:<24}", "Bank")?;
+  for coins in bank_income {
+    write!(f, " {:>10.2}", coins)?;
+  }
+  writeln!(f)
+}
+
+impl fmt::Display for CoinFlowReport {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let num_players = self.num_players;
+    let seats = (0..num_players)
+      .map(|seat| format!("Seat {}", seat))
+      .collect::<Vec<_>>();
+    writeln!(f, "Coins per game between seats")?;
+    write_matrix(
+      f,
+      &seats,
+      &self.by_seat.total_transfers(num_players),
+      &self.by_seat.total_bank_income(num_players),
+    )?;
+    writeln!(f)?;
+    writeln!(f, "Coins per game between strategies")?;
+    let num_strategies = self.strategies.len();
+    write_matrix(
+      f,
+      &self.strategies,
+      &self.by_strategy.total_transfers(num_strategies),
+      &self.by_strategy.total_bank_income(num_strategies),
+    )?;
+    writeln!(f)?;
+
+    // Sources sorted by the coins they move per game, most first
+    let mut sources = self
+      .by_seat
+      .transfers
+      .keys()
+      .chain(self.by_seat.bank_income.keys())
+      .collect::<Vec<_>>();
+    sources.sort();
+    sources.dedup();
+    let coins_of = |source: &String| {
+      let taken = self
+        .by_seat
+        .transfers
+        .get(source)
+        .map_or(0.0, |transfers| transfers.iter().flatten().sum::<f64>());
+      let bank = self
+        .by_seat
+        .bank_income
+        .get(source)
+        .map_or(0.0, |income| income.iter().sum::<f64>());
+      (taken, bank)
+    };
+    sources.sort_by(|a, b| {
+      let (taken_a, bank_a) = coins_of(a);
+      let (taken_b, bank_b) = coins_of(b);
+      (taken_b + bank_b).total_cmp(&(taken_a + bank_a))
+    });
+    writeln!(f, "{:<24} {:>10} {:>10}", "Source", "Taken", "Bank")?;
+    for source in sources {
+      let (taken, bank) = coins_of(source);
+      writeln!(f, "{:<24} {:>10.2} {:>10.2}", source, taken, bank)?;
+    }
+    writeln!(f)?;
+
+    let format_average = |average: Option<f64>| match average {
+      Some(average) => format!("{:+.2}", average),
+      None => "-".to_string(),
+    };
+    writeln!(
+      f,
+      "{:<24} {:>10} {:>10}",
+      "Per player and game", "Net taken", "Bank"
+    )?;
+    writeln!(
+      f,
+      "{:<24} {:>10} {:>10}",
+      "Winner",
+      format_average(self.winner_net_transfers),
+      format_average(self.winner_bank_income)
+    )?;
+    writeln!(
+      f,
+      "{:<24} {:>10} {:>10}",
+      "Others",
+      format_average(self.loser_net_transfers),
+      format_average(self.loser_bank_income)
+    )
+  }
+}
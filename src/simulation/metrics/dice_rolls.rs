@@ -0,0 +1,341 @@
+use std::fmt;
+
+use serde::Serialize;
+
+use crate::game::GameOutcome;
+use crate::models::player::OwnedCard;
+use crate::simulation::export::ResultRow;
+use crate::simulation::metric::Metric;
+use crate::simulation::simulator::GameResult;
+use crate::statistics::{
+  format_p_value, two_proportion_p_value, wilson_interval, ConfidenceInterval, CorrelationSums,
+};
+
+/// First round of every round bucket, the last bucket has no end
+const ROUND_BUCKETS: [usize; 4] = [0, 5, 10, 15];
+
+/// Rolls with one die and with two dice
+#[derive(Clone, Copy, Default, Serialize)]
+pub struct DiceCounts {
+  pub one_die: usize,
+  pub two_dice: usize,
+}
+
+impl DiceCounts {
+  fn add(&mut self, dice: u8) {
+    if dice == 2 {
+      self.two_dice += 1;
+    } else {
+      self.one_die += 1;
+    }
+  }
+
+  fn merge(&mut self, other: &DiceCounts) {
+    self.one_die += other.one_die;
+    self.two_dice += other.two_dice;
+  }
+
+  pub fn rolls(&self) -> usize {
+    self.one_die + self.two_dice
+  }
+
+  /// Share of the rolls made with two dice, None without rolls
+  pub fn two_dice_rate(&self) -> Option<f64> {
+    match self.rolls() {
+      0 => None,
+      rolls => Some(self.two_dice as f64 / rolls as f64),
+    }
+  }
+}
+
+/// Dice rolls of one strategy as winner and as loser
+#[derive(Clone, Default)]
+struct StrategyDiceCounts {
+  win_by_bucket: [DiceCounts; ROUND_BUCKETS.len()],
+  loss_by_bucket: [DiceCounts; ROUND_BUCKETS.len()],
+  // Share of two dice cards in the tableau against rolling two dice, one pair per roll
+  win_tableau_correlation: CorrelationSums,
+  loss_tableau_correlation: CorrelationSums,
+}
+
+/// How often every strategy rolls two dice instead of one, by round, as winner compared to as
+/// loser, over the games that had a winner. Also correlates the choice with the share of cards in
+/// the tableau that only two dice can activate.
+#[derive(Clone)]
+pub struct DiceRollMetric {
+  // Names of the distinct strategies, GameResult::seat_strategies indexes into this
+  strategies: Vec<String>,
+  counts: Vec<StrategyDiceCounts>,
+}
+
+/// Two dice rate of one strategy in one round bucket
+#[derive(Serialize)]
+pub struct RoundDiceRolls {
+  // First round of the bucket, counted from 0 as the rounds of Player::dice_rolls
+  pub from_round: usize,
+  // Last round of the bucket, null for the last bucket
+  pub to_round: Option<usize>,
+  pub win: DiceCounts,
+  pub loss: DiceCounts,
+  pub win_two_dice_interval: ConfidenceInterval,
+  pub loss_two_dice_interval: ConfidenceInterval,
+  // p-value of the test that the strategy rolls two dice as often when winning as when losing
+  pub two_dice_p_value: f64,
+}
+
+#[derive(Serialize)]
+pub struct StrategyDiceRolls {
+  pub strategy: String,
+  pub rounds: Vec<RoundDiceRolls>,
+  // Correlation between the share of two dice cards in the tableau and rolling two dice, null if
+  // either never varied
+  pub win_tableau_correlation: Option<f64>,
+  pub loss_tableau_correlation: Option<f64>,
+}
+
+#[derive(Serialize)]
+pub struct DiceRollReport {
+  pub dice_rolls_by_strategy: Vec<StrategyDiceRolls>,
+}
+
+impl DiceRollMetric {
+  pub fn new(strategies: Vec<String>) -> Self {
+    Self {
+      counts: vec![StrategyDiceCounts::default(); strategies.len()],
+      strategies,
+    }
+  }
+}
+
+/// Cards whose activation numbers can only be rolled with two dice
+fn needs_two_dice(owned: &OwnedCard) -> bool {
+  owned.card.def().activation.iter().all(|number| *number > 6)
+}
+
+impl Metric for DiceRollMetric {
+  type Report = DiceRollReport;
+
+  fn name(&self) -> String {
+    "Dice rolls".to_string()
+  }
+
+  fn observe_game(&mut self, game: &GameResult) {
+    let GameOutcome::Win(winner_index) = game.outcome else {
+      return;
+    };
+    for (seat, dice_rolls) in game.player_dice_rolls.iter().enumerate() {
+      let counts = &mut self.counts[game.seat_strategies[seat]];
+      let (by_bucket, correlation) = if seat == winner_index {
+        (
+          &mut counts.win_by_bucket,
+          &mut counts.win_tableau_correlation,
+        )
+      } else {
+        (
+          &mut counts.loss_by_bucket,
+          &mut counts.loss_tableau_correlation,
+        )
+      };
+      // Cards the seat gave away count for the rolls made while it owned them
+      let cards = game.player_cards[seat]
+        .iter()
+        .chain(game.player_retired_cards[seat].iter());
+      for roll in dice_rolls.iter() {
+        let bucket = ROUND_BUCKETS
+          .iter()
+          .rposition(|from| *from <= roll.round)
+          .unwrap_or(0);
+        by_bucket[bucket].add(roll.dice);
+
+        let tableau = cards.clone().filter(|owned| owned.owned_at(roll));
+        let (size, two_dice_cards) = tableau.fold((0, 0), |(size, two_dice_cards), owned| {
+          (size + 1, two_dice_cards + needs_two_dice(owned) as usize)
+        });
+        if size > 0 {
+          correlation.add(
+            two_dice_cards as f64 / size as f64,
+            (roll.dice == 2) as u8 as f64,
+          );
+        }
+      }
+    }
+  }
+
+  fn merge(&mut self, other: Self) {
+    for (counts, other_counts) in self.counts.iter_mut().zip(other.counts.iter()) {
+      for (bucket, other_bucket) in counts
+        .win_by_bucket
+        .iter_mut()
+        .zip(other_counts.win_by_bucket.iter())
+      {
+        bucket.merge(other_bucket);
+      }
+      for (bucket, other_bucket) in counts
+        .loss_by_bucket
+        .iter_mut()
+        .zip(other_counts.loss_by_bucket.iter())
+      {
+        bucket.merge(other_bucket);
+      }
+      counts
+        .win_tableau_correlation
+        .merge(&other_counts.win_tableau_correlation);
+      counts
+        .loss_tableau_correlation
+        .merge(&other_counts.loss_tableau_correlation);
+    }
+  }
+
+  fn finalize(self) -> DiceRollReport {
+    let dice_rolls_by_strategy = self
+      .strategies
+      .into_iter()
+      .zip(self.counts)
+      .map(|(strategy, counts)| StrategyDiceRolls {
+        strategy,
+        rounds: (0..ROUND_BUCKETS.len())
+          .map(|bucket| {
+            let (win, loss) = (counts.win_by_bucket[bucket], counts.loss_by_bucket[bucket]);
+            RoundDiceRolls {
+              from_round: ROUND_BUCKETS[bucket],
+              to_round: ROUND_BUCKETS.get(bucket + 1).map(|next| next - 1),
+              win,
+              loss,
+              win_two_dice_interval: wilson_interval(win.two_dice, win.rolls()),
+              loss_two_dice_interval: wilson_interval(loss.two_dice, loss.rolls()),
+              two_dice_p_value: two_proportion_p_value(
+                win.two_dice,
+                win.rolls(),
+                loss.two_dice,
+                loss.rolls(),
+              ),
+            }
+          })
+          .collect(),
+        win_tableau_correlation: counts.win_tableau_correlation.coefficient(),
+        loss_tableau_correlation: counts.loss_tableau_correlation.coefficient(),
+      })
+      .collect();
+    DiceRollReport {
+      dice_rolls_by_strategy,
+    }
+  }
+
+  fn rows(section: &str, report: &DiceRollReport) -> Vec<ResultRow> {
+    let mut rows = Vec::new();
+    for strategy in report.dice_rolls_by_strategy.iter() {
+      let name = strategy.strategy.as_str();
+      for rounds in strategy.rounds.iter() {
+        let round = rounds.from_round;
+        for (statistic, counts) in [("win", &rounds.win), ("loss", &rounds.loss)] {
+          rows.push(
+            ResultRow::new(
+              section,
+              &format!("one_die_rolls_{}", statistic),
+              counts.one_die as f64,
+            )
+            .key(name)
+            .round(round),
+          );
+          rows.push(
+            ResultRow::new(
+              section,
+              &format!("two_dice_rolls_{}", statistic),
+              counts.two_dice as f64,
+            )
+            .key(name)
+            .round(round),
+          );
+        }
+        // Rates of buckets without rolls are left out
+        if let Some(rate) = rounds.win.two_dice_rate() {
+          rows.push(
+            ResultRow::new(section, "two_dice_rate_win", rate)
+              .key(name)
+              .round(round)
+              .interval(&rounds.win_two_dice_interval),
+          );
+        }
+        if let Some(rate) = rounds.loss.two_dice_rate() {
+          rows.push(
+            ResultRow::new(section, "two_dice_rate_loss", rate)
+              .key(name)
+              .round(round)
+              .interval(&rounds.loss_two_dice_interval),
+          );
+        }
+        rows.push(
+          ResultRow::new(section, "two_dice_p_value", rounds.two_dice_p_value)
+            .key(name)
+            .round(round),
+        );
+      }
+      if let Some(correlation) = strategy.win_tableau_correlation {
+        rows.push(ResultRow::new(section, "tableau_correlation_win", correlation).key(name));
+      }
+      if let Some(correlation) = strategy.loss_tableau_correlation {
+        rows.push(ResultRow::new(section, "tableau_correlation_loss", correlation).key(name));
+      }
+    }
+    rows
+  }
+}
+
+impl RoundDiceRolls {
+  /// Rounds of the bucket, e.g. "R5-9" or "R15+"
+  pub fn label(&self) -> String {
+    match self.to_round {
+      Some(to_round) => format!("R{}-{}", self.from_round, to_round),
+      None => format!("R{}+", self.from_round),
+    }
+  }
+}
+
+impl fmt::Display for DiceRollReport {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let format_rate = |counts: &DiceCounts| match counts.two_dice_rate() {
+      Some(rate) => format!("{:.2}", rate * 100.0),
+      None => "-".to_string(),
+    };
+    let format_correlation = |correlation: Option<f64>| match correlation {
+      Some(correlation) => format!("{:+.3}", correlation),
+      None => "-".to_string(),
+    };
+    writeln!(
+      f,
+      "{:<24} {:>8} {:>8} {:>13} {:>8} {:>13} {:>8}",
+      "Strategy", "Rounds", "Win 2d%", "95% CI", "Loss 2d%", "95% CI", "p"
+    )?;
+    for strategy in self.dice_rolls_by_strategy.iter() {
+      for rounds in strategy.rounds.iter() {
+        writeln!(
+          f,
+          "{:<24} {:>8} {:>8} {:>13} {:>8} {:>13} {:>8}",
+          strategy.strategy,
+          rounds.label(),
+          format_rate(&rounds.win),
+          rounds.win_two_dice_interval.to_percent_string(),
+          format_rate(&rounds.loss),
+          rounds.loss_two_dice_interval.to_percent_string(),
+          format_p_value(rounds.two_dice_p_value)
+        )?;
+      }
+    }
+    writeln!(f)?;
+    writeln!(
+      f,
+      "{:<24} {:>12} {:>12}",
+      "Tableau correlation", "Win", "Loss"
+    )?;
+    for strategy in self.dice_rolls_by_strategy.iter() {
+      writeln!(
+        f,
+        "{:<24} {:>12} {:>12}",
+        strategy.strategy,
+        format_correlation(strategy.win_tableau_correlation),
+        format_correlation(strategy.loss_tableau_correlation)
+      )?;
+    }
+    Ok(())
+  }
+}
@@ -0,0 +1,416 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+use colored::Colorize;
+use serde::Serialize;
+use strum::{EnumIter, IntoEnumIterator};
+
+use crate::game::GameOutcome;
+use crate::models::player::OwnedLandmark;
+use crate::models::Landmark;
+use crate::simulation::export::ResultRow;
+use crate::simulation::metric::Metric;
+use crate::simulation::metrics::merge_counts;
+use crate::simulation::simulator::GameResult;
+use crate::statistics::{
+  format_p_value, two_proportion_p_value, wilson_interval, ConfidenceInterval,
+};
+
+/// How a game with a winner was won
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, PartialOrd, Ord, EnumIter, Serialize)]
+pub enum WinType {
+  // The winner built the Launch Pad
+  LaunchPad,
+  // The winner built the required number of landmarks
+  Landmarks,
+  // Every other player forfeited
+  LastPlayerStanding,
+}
+
+impl WinType {
+  fn of(game: &GameResult, winner_index: usize) -> Self {
+    // Same order as Game::winner, the last player standing wins before landmarks are checked
+    let others_forfeited = game
+      .player_forfeited
+      .iter()
+      .enumerate()
+      .all(|(index, forfeited)| index == winner_index || *forfeited);
+    if others_forfeited {
+      WinType::LastPlayerStanding
+    } else if game.player_landmarks[winner_index]
+      .iter()
+      .any(|owned| owned.landmark == Landmark::LaunchPad)
+    {
+      WinType::LaunchPad
+    } else {
+      WinType::Landmarks
+    }
+  }
+}
+
+/// Sums to average the purchases of a landmark
+#[derive(Clone, Default)]
+struct PurchaseSums {
+  purchases: usize,
+  // Position among the landmarks of the buyer, 1 being the first one built
+  order: usize,
+  round: usize,
+}
+
+impl PurchaseSums {
+  fn add(&mut self, order: usize, round: usize) {
+    self.purchases += 1;
+    self.order += order;
+    self.round += round;
+  }
+
+  fn merge(&mut self, other: &PurchaseSums) {
+    self.purchases += other.purchases;
+    self.order += other.order;
+    self.round += other.round;
+  }
+
+  /// Average purchase order and round, None if the landmark was never bought
+  fn averages(&self) -> Option<(f64, f64)> {
+    match self.purchases {
+      0 => None,
+      purchases => Some((
+        self.order as f64 / purchases as f64,
+        self.round as f64 / purchases as f64,
+      )),
+    }
+  }
+}
+
+/// Landmark presence, purchase order and round of the winner compared to the other players, the
+/// landmark that ended the game and how games were won, over the games that had a winner
+#[derive(Clone)]
+pub struct LandmarkMetric {
+  num_players: usize,
+  games_with_winner: usize,
+  // Present landmark counts for the winner HashMap<Landmark, count>
+  win_present_counts: HashMap<Landmark, usize>,
+  // Present landmark counts for the other players HashMap<Landmark, count>
+  loss_present_counts: HashMap<Landmark, usize>,
+  win_purchases: HashMap<Landmark, PurchaseSums>,
+  loss_purchases: HashMap<Landmark, PurchaseSums>,
+  // Last landmark built by the winner, for games won by building landmarks
+  game_ending_counts: HashMap<Landmark, usize>,
+  win_type_counts: HashMap<WinType, usize>,
+  win_type_round_sums: HashMap<WinType, usize>,
+}
+
+#[derive(Serialize)]
+pub struct LandmarkReport {
+  pub landmark_p_present_win: BTreeMap<Landmark, f64>,
+  pub landmark_p_present_loss: BTreeMap<Landmark, f64>,
+  pub landmark_p_present_win_interval: BTreeMap<Landmark, ConfidenceInterval>,
+  pub landmark_p_present_loss_interval: BTreeMap<Landmark, ConfidenceInterval>,
+  // p-value of the test that a landmark is as often built by the winner as by the other players
+  pub landmark_presence_p_value: BTreeMap<Landmark, f64>,
+  // Average position among the landmarks of the buyer (1 is the first built), null if never built
+  pub landmark_purchase_order_win: BTreeMap<Landmark, Option<f64>>,
+  pub landmark_purchase_order_loss: BTreeMap<Landmark, Option<f64>>,
+  // Average round the landmark was built in, counted from 0 as OwnedLandmark::bought_round
+  pub landmark_purchase_round_win: BTreeMap<Landmark, Option<f64>>,
+  pub landmark_purchase_round_loss: BTreeMap<Landmark, Option<f64>>,
+  // Games ended by building the landmark
+  pub landmark_game_ending_counts: BTreeMap<Landmark, usize>,
+  pub win_type_counts: BTreeMap<WinType, usize>,
+  // Share of the games with a winner that were won this way
+  pub win_type_interval: BTreeMap<WinType, ConfidenceInterval>,
+  // Average rounds of the games won this way, null if no game was
+  pub win_type_average_rounds: BTreeMap<WinType, Option<f64>>,
+}
+
+impl LandmarkMetric {
+  pub fn new(num_players: usize) -> Self {
+    Self {
+      num_players,
+      games_with_winner: 0,
+      win_present_counts: HashMap::new(),
+      loss_present_counts: HashMap::new(),
+      win_purchases: HashMap::new(),
+      loss_purchases: HashMap::new(),
+      game_ending_counts: HashMap::new(),
+      win_type_counts: HashMap::new(),
+      win_type_round_sums: HashMap::new(),
+    }
+  }
+}
+
+impl Metric for LandmarkMetric {
+  type Report = LandmarkReport;
+
+  fn name(&self) -> String {
+    "Landmarks".to_string()
+  }
+
+  fn observe_game(&mut self, game: &GameResult) {
+    let GameOutcome::Win(winner_index) = game.outcome else {
+      return;
+    };
+    self.games_with_winner += 1;
+
+    for (player_index, landmarks) in game.player_landmarks.iter().enumerate() {
+      let (presence, purchases) = if player_index == winner_index {
+        (&mut self.win_present_counts, &mut self.win_purchases)
+      } else {
+        (&mut self.loss_present_counts, &mut self.loss_purchases)
+      };
+      // Landmarks are unique, so every owned landmark is a distinct one
+      for (order, owned) in landmarks.iter().enumerate() {
+        *presence.entry(owned.landmark).or_insert(0) += 1;
+        purchases
+          .entry(owned.landmark)
+          .or_default()
+          .add(order + 1, owned.bought_round);
+      }
+    }
+
+    let win_type = WinType::of(game, winner_index);
+    *self.win_type_counts.entry(win_type).or_insert(0) += 1;
+    *self.win_type_round_sums.entry(win_type).or_insert(0) += game.rounds;
+    if win_type != WinType::LastPlayerStanding {
+      // Landmarks are appended as they are built, the last one ended the game
+      if let Some(OwnedLandmark { landmark, .. }) = game.player_landmarks[winner_index].last() {
+        *self.game_ending_counts.entry(*landmark).or_insert(0) += 1;
+      }
+    }
+  }
+
+  fn merge(&mut self, other: Self) {
+    self.games_with_winner += other.games_with_winner;
+    merge_counts(&mut self.win_present_counts, other.win_present_counts);
+    merge_counts(&mut self.loss_present_counts, other.loss_present_counts);
+    for (purchases, other_purchases) in [
+      (&mut self.win_purchases, other.win_purchases),
+      (&mut self.loss_purchases, other.loss_purchases),
+    ] {
+      for (landmark, sums) in other_purchases {
+        purchases.entry(landmark).or_default().merge(&sums);
+      }
+    }
+    merge_counts(&mut self.game_ending_counts, other.game_ending_counts);
+    merge_counts(&mut self.win_type_counts, other.win_type_counts);
+    merge_counts(&mut self.win_type_round_sums, other.win_type_round_sums);
+  }
+
+  fn finalize(self) -> LandmarkReport {
+    let wins = self.games_with_winner.max(1);
+    let losses = wins * (self.num_players - 1);
+    let averages = |purchases: &HashMap<Landmark, PurchaseSums>, landmark: &Landmark| {
+      purchases.get(landmark).and_then(PurchaseSums::averages)
+    };
+    let mut report = LandmarkReport {
+      landmark_p_present_win: BTreeMap::new(),
+      landmark_p_present_loss: BTreeMap::new(),
+      landmark_p_present_win_interval: BTreeMap::new(),
+      landmark_p_present_loss_interval: BTreeMap::new(),
+      landmark_presence_p_value: BTreeMap::new(),
+      landmark_purchase_order_win: BTreeMap::new(),
+      landmark_purchase_order_loss: BTreeMap::new(),
+      landmark_purchase_round_win: BTreeMap::new(),
+      landmark_purchase_round_loss: BTreeMap::new(),
+      landmark_game_ending_counts: BTreeMap::new(),
+      win_type_counts: BTreeMap::new(),
+      win_type_interval: BTreeMap::new(),
+      win_type_average_rounds: BTreeMap::new(),
+    };
+    for landmark in Landmark::iter() {
+      let win_present = *self.win_present_counts.get(&landmark).unwrap_or(&0);
+      let loss_present = *self.loss_present_counts.get(&landmark).unwrap_or(&0);
+      report
+        .landmark_p_present_win
+        .insert(landmark, win_present as f64 / wins as f64);
+      report
+        .landmark_p_present_loss
+        .insert(landmark, loss_present as f64 / losses as f64);
+      report
+        .landmark_p_present_win_interval
+        .insert(landmark, wilson_interval(win_present, wins));
+      report
+        .landmark_p_present_loss_interval
+        .insert(landmark, wilson_interval(loss_present, losses));
+      report.landmark_presence_p_value.insert(
+        landmark,
+        two_proportion_p_value(win_present, wins, loss_present, losses),
+      );
+
+      let win_averages = averages(&self.win_purchases, &landmark);
+      let loss_averages = averages(&self.loss_purchases, &landmark);
+      report
+        .landmark_purchase_order_win
+        .insert(landmark, win_averages.map(|(order, _)| order));
+      report
+        .landmark_purchase_order_loss
+        .insert(landmark, loss_averages.map(|(order, _)| order));
+      report
+        .landmark_purchase_round_win
+        .insert(landmark, win_averages.map(|(_, round)| round));
+      report
+        .landmark_purchase_round_loss
+        .insert(landmark, loss_averages.map(|(_, round)| round));
+      report.landmark_game_ending_counts.insert(
+        landmark,
+        *self.game_ending_counts.get(&landmark).unwrap_or(&0),
+      );
+    }
+    for win_type in WinType::iter() {
+      let count = *self.win_type_counts.get(&win_type).unwrap_or(&0);
+      let rounds = *self.win_type_round_sums.get(&win_type).unwrap_or(&0);
+      report.win_type_counts.insert(win_type, count);
+      report
+        .win_type_interval
+        .insert(win_type, wilson_interval(count, self.games_with_winner));
+      report
+        .win_type_average_rounds
+        .insert(win_type, (count > 0).then(|| rounds as f64 / count as f64));
+    }
+    report
+  }
+
+  fn rows(section: &str, report: &LandmarkReport) -> Vec<ResultRow> {
+    let mut rows = Vec::new();
+    for landmark in Landmark::iter() {
+      let name = landmark.def().name;
+      rows.push(
+        ResultRow::new(
+          section,
+          "p_present_win",
+          report.landmark_p_present_win[&landmark],
+        )
+        .key(name)
+        .interval(&report.landmark_p_present_win_interval[&landmark]),
+      );
+      rows.push(
+        ResultRow::new(
+          section,
+          "p_present_loss",
+          report.landmark_p_present_loss[&landmark],
+        )
+        .key(name)
+        .interval(&report.landmark_p_present_loss_interval[&landmark]),
+      );
+      rows.push(
+        ResultRow::new(
+          section,
+          "presence_p_value",
+          report.landmark_presence_p_value[&landmark],
+        )
+        .key(name),
+      );
+      // Averages of landmarks that were never built are left out
+      for (statistic, average) in [
+        ("purchase_order_win", &report.landmark_purchase_order_win),
+        ("purchase_order_loss", &report.landmark_purchase_order_loss),
+        ("purchase_round_win", &report.landmark_purchase_round_win),
+        ("purchase_round_loss", &report.landmark_purchase_round_loss),
+      ] {
+        if let Some(average) = average[&landmark] {
+          rows.push(ResultRow::new(section, statistic, average).key(name));
+        }
+      }
+      rows.push(
+        ResultRow::new(
+          section,
+          "game_ending_count",
+          report.landmark_game_ending_counts[&landmark] as f64,
+        )
+        .key(name),
+      );
+    }
+    for win_type in WinType::iter() {
+      let name = format!("{:?}", win_type);
+      rows.push(
+        ResultRow::new(
+          section,
+          "win_type_count",
+          report.win_type_counts[&win_type] as f64,
+        )
+        .key(&name)
+        .interval(&report.win_type_interval[&win_type]),
+      );
+      if let Some(rounds) = report.win_type_average_rounds[&win_type] {
+        rows.push(ResultRow::new(section, "win_type_average_rounds", rounds).key(&name));
+      }
+    }
+    rows
+  }
+}
+
+impl LandmarkReport {
+  /// Landmarks sorted by how much more often the winner builds them, best first
+  pub fn landmarks_by_difference(&self) -> Vec<Landmark> {
+    let mut landmarks = Landmark::iter().collect::<Vec<_>>();
+    landmarks.sort_by(|a, b| {
+      let diff_a = self.landmark_p_present_win[a] - self.landmark_p_present_loss[a];
+      let diff_b = self.landmark_p_present_win[b] - self.landmark_p_present_loss[b];
+      diff_b.total_cmp(&diff_a)
+    });
+    landmarks
+  }
+}
+
+impl fmt::Display for LandmarkReport {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let format_average = |average: Option<f64>| match average {
+      Some(average) => format!("{:.1}", average),
+      None => "-".to_string(),
+    };
+    writeln!(
+      f,
+      "{:<20} {:>8} {:>13} {:>8} {:>13} {:>8} {:>8} {:>10} {:>10} {:>8}",
+      "Landmark", "Win", "95% CI", "Loss", "95% CI", "Diff", "p", "Order W/L", "Round W/L", "Ended"
+    )?;
+    for landmark in self.landmarks_by_difference() {
+      let p_win = self.landmark_p_present_win[&landmark];
+      let p_loss = self.landmark_p_present_loss[&landmark];
+      let diff = format!("{:+.3}", p_win - p_loss);
+      let diff = if p_win >= p_loss {
+        diff.green()
+      } else {
+        diff.red()
+      };
+      writeln!(
+        f,
+        "{:<20} {:>8.3} {:>13} {:>8.3} {:>13} {:>8} {:>8} {:>10} {:>10} {:>8}",
+        landmark.def().name,
+        p_win,
+        self.landmark_p_present_win_interval[&landmark],
+        p_loss,
+        self.landmark_p_present_loss_interval[&landmark],
+        diff,
+        format_p_value(self.landmark_presence_p_value[&landmark]),
+        format!(
+          "{}/{}",
+          format_average(self.landmark_purchase_order_win[&landmark]),
+          format_average(self.landmark_purchase_order_loss[&landmark])
+        ),
+        format!(
+          "{}/{}",
+          format_average(self.landmark_purchase_round_win[&landmark]),
+          format_average(self.landmark_purchase_round_loss[&landmark])
+        ),
+        self.landmark_game_ending_counts[&landmark]
+      )?;
+    }
+    writeln!(f)?;
+    writeln!(
+      f,
+      "{:<20} {:>8} {:>13} {:>10}",
+      "Win type", "Wins", "95% CI", "Avg rounds"
+    )?;
+    for win_type in WinType::iter() {
+      writeln!(
+        f,
+        "{:<20} {:>8} {:>13} {:>10}",
+        format!("{:?}", win_type),
+        self.win_type_counts[&win_type],
+        self.win_type_interval[&win_type].to_percent_string(),
+        format_average(self.win_type_average_rounds[&win_type])
+      )?;
+    }
+    Ok(())
+  }
+}
@@ -0,0 +1,34 @@
+mod card_income;
+mod card_presence;
+mod card_presence_by_round;
+mod coin_flow;
+mod dice_rolls;
+mod landmarks;
+mod seat_wins;
+mod strategy_wins;
+
+pub use card_income::{CardIncomeMetric, CardIncomeReport, CardReturn};
+pub use card_presence::{CardPresenceMetric, CardPresenceReport};
+pub use card_presence_by_round::{CardPresenceByRoundMetric, CardPresenceByRoundReport};
+pub use coin_flow::{CoinFlow, CoinFlowMetric, CoinFlowReport, CoinFlowRow};
+pub use dice_rolls::{
+  DiceCounts, DiceRollMetric, DiceRollReport, RoundDiceRolls, StrategyDiceRolls,
+};
+pub use landmarks::{LandmarkMetric, LandmarkReport, WinType};
+pub use seat_wins::{SeatWinMetric, SeatWinReport};
+pub use strategy_wins::{StrategyResult, StrategyWinMetric, StrategyWinReport};
+
+use std::collections::HashMap;
+use std::hash::Hash;
+
+pub(crate) fn merge_counts<K: Hash + Eq>(counts: &mut HashMap<K, usize>, other: HashMap<K, usize>) {
+  for (key, count) in other {
+    *counts.entry(key).or_insert(0) += count;
+  }
+}
+
+pub(crate) fn add_counts(counts: &mut [usize], other: &[usize]) {
+  for (count, other_count) in counts.iter_mut().zip(other) {
+    *count += other_count;
+  }
+}
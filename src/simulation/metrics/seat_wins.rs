@@ -0,0 +1,161 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+use serde::Serialize;
+
+use crate::game::{DrawReason, GameOutcome};
+use crate::simulation::export::ResultRow;
+use crate::simulation::metric::Metric;
+use crate::simulation::metrics::{add_counts, merge_counts};
+use crate::simulation::simulator::GameResult;
+use crate::statistics::{
+  chi_square_uniform_test, format_p_value, wilson_interval, ConfidenceInterval,
+};
+
+/// Wins, illegal decisions and draws by seat
+#[derive(Clone)]
+pub struct SeatWinMetric {
+  game_count: usize,
+  // Win counts by player index
+  win_count_by_player_index: Vec<usize>,
+  // Illegal decision counts by player index
+  violation_count_by_player_index: Vec<usize>,
+  // Games that ended in a draw HashMap<DrawReason, count>
+  draw_counts: HashMap<DrawReason, usize>,
+}
+
+#[derive(Serialize)]
+pub struct SeatWinReport {
+  // Games played, draws included
+  pub game_count: usize,
+  pub win_count_by_player_index: Vec<usize>,
+  // Win rate of every seat over all games, draws included
+  pub win_rate_interval_by_player_index: Vec<ConfidenceInterval>,
+  // p-value of the chi-square test that every seat wins equally often
+  pub seat_win_p_value: f64,
+  pub violation_count_by_player_index: Vec<usize>,
+  pub draw_counts: BTreeMap<DrawReason, usize>,
+}
+
+impl SeatWinMetric {
+  pub fn new(num_players: usize) -> Self {
+    Self {
+      game_count: 0,
+      win_count_by_player_index: vec![0; num_players],
+      violation_count_by_player_index: vec![0; num_players],
+      draw_counts: HashMap::new(),
+    }
+  }
+
+  /// Games with a winner
+  pub fn games_with_winner(&self) -> usize {
+    self.win_count_by_player_index.iter().sum()
+  }
+}
+
+impl Metric for SeatWinMetric {
+  type Report = SeatWinReport;
+
+  fn name(&self) -> String {
+    "Seat wins".to_string()
+  }
+
+  fn observe_game(&mut self, game: &GameResult) {
+    self.game_count += 1;
+    for violation in game.violations.iter() {
+      self.violation_count_by_player_index[violation.player_index] += 1;
+    }
+    match game.outcome {
+      GameOutcome::Win(winner_index) => self.win_count_by_player_index[winner_index] += 1,
+      GameOutcome::Draw(reason) => *self.draw_counts.entry(reason).or_insert(0) += 1,
+    }
+  }
+
+  fn merge(&mut self, other: Self) {
+    self.game_count += other.game_count;
+    add_counts(
+      &mut self.win_count_by_player_index,
+      &other.win_count_by_player_index,
+    );
+    add_counts(
+      &mut self.violation_count_by_player_index,
+      &other.violation_count_by_player_index,
+    );
+    merge_counts(&mut self.draw_counts, other.draw_counts);
+  }
+
+  fn finalize(self) -> SeatWinReport {
+    let (_, seat_win_p_value) = chi_square_uniform_test(&self.win_count_by_player_index);
+    SeatWinReport {
+      game_count: self.game_count,
+      win_rate_interval_by_player_index: self
+        .win_count_by_player_index
+        .iter()
+        .map(|wins| wilson_interval(*wins, self.game_count))
+        .collect(),
+      win_count_by_player_index: self.win_count_by_player_index,
+      seat_win_p_value,
+      violation_count_by_player_index: self.violation_count_by_player_index,
+      draw_counts: self.draw_counts.into_iter().collect(),
+    }
+  }
+
+  fn rows(section: &str, report: &SeatWinReport) -> Vec<ResultRow> {
+    let mut rows = Vec::new();
+    for (seat, wins) in report.win_count_by_player_index.iter().enumerate() {
+      rows.push(ResultRow::new(section, "win_count", *wins as f64).seat(seat));
+      rows.push(
+        ResultRow::new(
+          section,
+          "win_rate",
+          *wins as f64 / report.game_count.max(1) as f64,
+        )
+        .seat(seat)
+        .interval(&report.win_rate_interval_by_player_index[seat]),
+      );
+      rows.push(
+        ResultRow::new(
+          section,
+          "illegal_decisions",
+          report.violation_count_by_player_index[seat] as f64,
+        )
+        .seat(seat),
+      );
+    }
+    rows.push(ResultRow::new(
+      section,
+      "seat_win_p_value",
+      report.seat_win_p_value,
+    ));
+    for (reason, count) in report.draw_counts.iter() {
+      rows.push(ResultRow::new(section, "draw_count", *count as f64).key(&format!("{:?}", reason)));
+    }
+    rows
+  }
+}
+
+impl fmt::Display for SeatWinReport {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    for (index, wins) in self.win_count_by_player_index.iter().enumerate() {
+      writeln!(
+        f,
+        "Player {} wins: {} ({}) | illegal decisions: {}",
+        index,
+        wins,
+        self.win_rate_interval_by_player_index[index].to_percent_string(),
+        self.violation_count_by_player_index[index]
+      )?;
+    }
+    writeln!(
+      f,
+      "Seats win equally often: p = {}",
+      format_p_value(self.seat_win_p_value)
+    )?;
+    for reason in [DrawReason::TurnLimit, DrawReason::Stalemate] {
+      if let Some(count) = self.draw_counts.get(&reason) {
+        writeln!(f, "Draws ({:?}): {}", reason, count)?;
+      }
+    }
+    Ok(())
+  }
+}
@@ -0,0 +1,188 @@
+use std::fmt;
+
+use serde::Serialize;
+
+use crate::simulation::export::ResultRow;
+use crate::simulation::metric::Metric;
+use crate::simulation::metrics::add_counts;
+use crate::simulation::simulator::GameResult;
+use crate::statistics::{wilson_interval, ConfidenceInterval};
+
+/// Games and wins of every distinct strategy of the lineup, aggregated over the seats it played
+#[derive(Clone)]
+pub struct StrategyWinMetric {
+  // Names of the distinct strategies, GameResult::seat_strategies indexes into this
+  strategies: Vec<String>,
+  // Games and wins by strategy and seat [strategy][seat]
+  games_by_seat: Vec<Vec<usize>>,
+  wins_by_seat: Vec<Vec<usize>>,
+}
+
+/// Games and wins of one strategy, in total and by seat. Games include draws.
+#[derive(Serialize)]
+pub struct StrategyResult {
+  pub strategy: String,
+  pub games: usize,
+  pub wins: usize,
+  pub games_by_seat: Vec<usize>,
+  pub wins_by_seat: Vec<usize>,
+  pub win_rate_interval: ConfidenceInterval,
+  pub win_rate_interval_by_seat: Vec<ConfidenceInterval>,
+}
+
+impl StrategyResult {
+  pub fn win_rate(&self) -> f64 {
+    self.wins as f64 / self.games.max(1) as f64
+  }
+
+  /// Win rate when playing in the given seat, None if the strategy never played there
+  pub fn win_rate_in_seat(&self, seat: usize) -> Option<f64> {
+    match self.games_by_seat[seat] {
+      0 => None,
+      games => Some(self.wins_by_seat[seat] as f64 / games as f64),
+    }
+  }
+}
+
+#[derive(Serialize)]
+pub struct StrategyWinReport {
+  pub strategy_results: Vec<StrategyResult>,
+}
+
+impl StrategyWinMetric {
+  pub fn new(num_players: usize, strategies: Vec<String>) -> Self {
+    let by_strategy_and_seat = vec![vec![0; num_players]; strategies.len()];
+    Self {
+      strategies,
+      games_by_seat: by_strategy_and_seat.clone(),
+      wins_by_seat: by_strategy_and_seat,
+    }
+  }
+
+  /// Games and wins of the strategy with the given index over all seats
+  pub fn games_and_wins(&self, strategy: usize) -> (usize, usize) {
+    (
+      self.games_by_seat[strategy].iter().sum(),
+      self.wins_by_seat[strategy].iter().sum(),
+    )
+  }
+}
+
+impl Metric for StrategyWinMetric {
+  type Report = StrategyWinReport;
+
+  fn name(&self) -> String {
+    "Strategy wins".to_string()
+  }
+
+  fn observe_game(&mut self, game: &GameResult) {
+    // Games are counted for every seat, draws included
+    for (seat, strategy) in game.seat_strategies.iter().enumerate() {
+      self.games_by_seat[*strategy][seat] += 1;
+    }
+    if let Some(winner_index) = game.outcome.winner() {
+      self.wins_by_seat[game.seat_strategies[winner_index]][winner_index] += 1;
+    }
+  }
+
+  fn merge(&mut self, other: Self) {
+    for (counts, other_counts) in self
+      .games_by_seat
+      .iter_mut()
+      .zip(other.games_by_seat.iter())
+    {
+      add_counts(counts, other_counts);
+    }
+    for (counts, other_counts) in self.wins_by_seat.iter_mut().zip(other.wins_by_seat.iter()) {
+      add_counts(counts, other_counts);
+    }
+  }
+
+  fn finalize(self) -> StrategyWinReport {
+    let strategy_results = self
+      .strategies
+      .into_iter()
+      .zip(self.games_by_seat.into_iter().zip(self.wins_by_seat))
+      .map(|(strategy, (games_by_seat, wins_by_seat))| {
+        let games = games_by_seat.iter().sum();
+        let wins = wins_by_seat.iter().sum();
+        StrategyResult {
+          strategy,
+          games,
+          wins,
+          win_rate_interval: wilson_interval(wins, games),
+          win_rate_interval_by_seat: wins_by_seat
+            .iter()
+            .zip(games_by_seat.iter())
+            .map(|(wins, games)| wilson_interval(*wins, *games))
+            .collect(),
+          games_by_seat,
+          wins_by_seat,
+        }
+      })
+      .collect();
+    StrategyWinReport { strategy_results }
+  }
+
+  fn rows(section: &str, report: &StrategyWinReport) -> Vec<ResultRow> {
+    let mut rows = Vec::new();
+    for strategy in report.strategy_results.iter() {
+      let name = strategy.strategy.as_str();
+      rows.push(ResultRow::new(section, "games", strategy.games as f64).key(name));
+      rows.push(ResultRow::new(section, "wins", strategy.wins as f64).key(name));
+      rows.push(
+        ResultRow::new(section, "win_rate", strategy.win_rate())
+          .key(name)
+          .interval(&strategy.win_rate_interval),
+      );
+      for seat in 0..strategy.games_by_seat.len() {
+        if let Some(win_rate) = strategy.win_rate_in_seat(seat) {
+          rows.push(
+            ResultRow::new(section, "win_rate", win_rate)
+              .key(name)
+              .seat(seat)
+              .interval(&strategy.win_rate_interval_by_seat[seat]),
+          );
+        }
+      }
+    }
+    rows
+  }
+}
+
+impl fmt::Display for StrategyWinReport {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let num_players = self
+      .strategy_results
+      .first()
+      .map_or(0, |strategy| strategy.games_by_seat.len());
+    write!(
+      f,
+      "{:<24} {:>8} {:>8} {:>8} {:>13}",
+      "Strategy", "Games", "Wins", "Win %", "95% CI"
+    )?;
+    for seat in 0..num_players {
+      write!(f, " {:>8}", format!("Seat {}", seat))?;
+    }
+    writeln!(f)?;
+    for strategy in self.strategy_results.iter() {
+      write!(
+        f,
+        "{:<24} {:>8} {:>8} {:>8.2} {:>13}",
+        strategy.strategy,
+        strategy.games,
+        strategy.wins,
+        strategy.win_rate() * 100.0,
+        strategy.win_rate_interval.to_percent_string()
+      )?;
+      for seat in 0..strategy.games_by_seat.len() {
+        match strategy.win_rate_in_seat(seat) {
+          Some(win_rate) => write!(f, " {:>8.2}", win_rate * 100.0)?,
+          None => write!(f, " {:>8}", "-")?,
+        }
+      }
+      writeln!(f)?;
+    }
+    Ok(())
+  }
+}
@@ -1,5 +1,17 @@
 mod accumulator;
+mod export;
+mod metric;
+pub mod metrics;
+mod rating;
+mod sequential;
 mod simulator;
+mod tournament;
 
 pub use accumulator::SimulationResult;
-pub use simulator::Simulator;
+pub use export::{save_game_rows, save_json_or_csv, ExportError, GameRow, GameRows, ResultRow};
+pub use metric::{Metric, MetricSection};
+pub use metrics::StrategyResult;
+pub use rating::{update_ratings, Rating};
+pub use sequential::{SequentialResult, SequentialTest, StoppingCriterion};
+pub use simulator::{GameResult, SeatingMode, SimulationError, Simulator};
+pub use tournament::{round_robin_lineups, Standing, TournamentResult};
@@ -0,0 +1,132 @@
+use serde::{Deserialize, Serialize};
+
+const DEFAULT_MU: f64 = 25.0;
+const DEFAULT_SIGMA: f64 = DEFAULT_MU / 3.0;
+// Performance spread of a single game
+const BETA: f64 = DEFAULT_SIGMA / 2.0;
+// Lower bound of the variance factor, keeps sigma from collapsing to zero
+const KAPPA: f64 = 0.0001;
+
+/// Skill estimate of a strategy as a normal distribution, in the style of TrueSkill
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct Rating {
+  pub mu: f64,
+  pub sigma: f64,
+}
+
+impl Default for Rating {
+  fn default() -> Self {
+    Self {
+      mu: DEFAULT_MU,
+      sigma: DEFAULT_SIGMA,
+    }
+  }
+}
+
+impl Rating {
+  /// Skill the strategy has with high certainty, used to rank the leaderboard
+  pub fn conservative(&self) -> f64 {
+    self.mu - 3.0 * self.sigma
+  }
+}
+
+/// Updates the ratings of the players of one game from their placements (1 being the best),
+/// with the Bradley-Terry full pairing update of Weng and Lin. Every player is compared with
+/// every other player of the game, equal placements count as half a win.
+pub fn update_ratings(ratings: &mut [Rating], placements: &[usize]) {
+  let old = ratings.to_vec();
+  for (index, rating) in ratings.iter_mut().enumerate() {
+    let variance = old[index].sigma.powi(2);
+    let mut omega = 0.0;
+    let mut delta = 0.0;
+    for (other, other_rating) in old.iter().enumerate() {
+      if other == index {
+        continue;
+      }
+      let c = (variance + other_rating.sigma.powi(2) + 2.0 * BETA.powi(2)).sqrt();
+      let p = 1.0 / (1.0 + ((other_rating.mu - old[index].mu) / c).exp());
+      let score = match placements[index].cmp(&placements[other]) {
+        std::cmp::Ordering::Less => 1.0,
+        std::cmp::Ordering::Equal => 0.5,
+        std::cmp::Ordering::Greater => 0.0,
+      };
+      let gamma = old[index].sigma / c;
+      omega += variance / c * (score - p);
+      delta += gamma * variance / c.powi(2) * p * (1.0 - p);
+    }
+    rating.mu += omega;
+    rating.sigma *= (1.0 - delta).max(KAPPA).sqrt();
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn assert_close(actual: f64, expected: f64) {
+    assert!(
+      (actual - expected).abs() < 1e-9,
+      "expected {}, got {}",
+      expected,
+      actual
+    );
+  }
+
+  #[test]
+  fn two_player_game_matches_known_values() {
+    let mut ratings = [Rating::default(); 2];
+    update_ratings(&mut ratings, &[1, 2]);
+    assert_close(ratings[0].mu, 27.63523138347365);
+    assert_close(ratings[1].mu, 22.36476861652635);
+    assert_close(ratings[0].sigma, 8.065506316323548);
+    assert_close(ratings[1].sigma, 8.065506316323548);
+  }
+
+  #[test]
+  fn draw_between_equal_players_keeps_their_skill() {
+    let mut ratings = [Rating::default(); 3];
+    update_ratings(&mut ratings, &[1, 1, 1]);
+    for rating in ratings.iter() {
+      assert_close(rating.mu, DEFAULT_MU);
+      assert!(rating.sigma < DEFAULT_SIGMA);
+    }
+  }
+
+  #[test]
+  fn placements_order_the_ratings() {
+    let mut ratings = [Rating::default(); 4];
+    update_ratings(&mut ratings, &[3, 1, 4, 2]);
+    assert!(ratings[1].mu > ratings[3].mu);
+    assert!(ratings[3].mu > ratings[0].mu);
+    assert!(ratings[0].mu > ratings[2].mu);
+    // The update is zero-sum between players with the same uncertainty
+    let total: f64 = ratings.iter().map(|rating| rating.mu).sum();
+    assert_close(total, 4.0 * DEFAULT_MU);
+  }
+
+  #[test]
+  fn upset_moves_ratings_more_than_expected_result() {
+    let strong = Rating {
+      mu: 35.0,
+      sigma: 4.0,
+    };
+    let weak = Rating {
+      mu: 15.0,
+      sigma: 4.0,
+    };
+    let mut expected = [strong, weak];
+    update_ratings(&mut expected, &[1, 2]);
+    let mut upset = [strong, weak];
+    update_ratings(&mut upset, &[2, 1]);
+    assert!(upset[1].mu - weak.mu > expected[0].mu - strong.mu);
+  }
+
+  #[test]
+  fn sigma_never_collapses() {
+    let mut ratings = [Rating::default(); 2];
+    for _ in 0..10_000 {
+      update_ratings(&mut ratings, &[1, 2]);
+    }
+    assert!(ratings.iter().all(|rating| rating.sigma > 0.0));
+  }
+}
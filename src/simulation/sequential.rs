@@ -0,0 +1,123 @@
+use serde::Serialize;
+
+use crate::models::Card;
+use crate::player_strategies::registry::StrategySpec;
+use crate::simulation::accumulator::{SimulationAccumulator, SimulationResult};
+use crate::simulation::simulator::{SimulationError, Simulator};
+use crate::statistics::{obrien_fleming_spending, two_proportion_p_value};
+
+/// Difference a sequential simulation waits for
+#[derive(Clone, Debug)]
+pub enum StoppingCriterion {
+  // Difference between the win rates of two strategies of the lineup
+  WinRate(StrategySpec, StrategySpec),
+  // Difference between the presence of a card for the winner and for the other players
+  CardPresence(Card),
+}
+
+/// Plays batches of games until the difference of the criterion is significant or the maximum
+/// number of games is reached
+#[derive(Clone, Debug)]
+pub struct SequentialTest {
+  pub criterion: StoppingCriterion,
+  // Overall significance level, shared out over all looks at the data
+  pub significance: f64,
+  pub max_games: usize,
+  // The test looks at the data after every batch
+  pub batch_size: usize,
+}
+
+// Stopping criterion with the strategies resolved to their index in the lineup
+enum Measure {
+  WinRate(usize, usize),
+  CardPresence(Card),
+}
+
+#[derive(Serialize)]
+pub struct SequentialResult {
+  // Games played until the simulation stopped
+  pub game_count: usize,
+  // Difference of the criterion when the simulation stopped, e.g. win rate A - win rate B
+  pub difference: f64,
+  pub p_value: f64,
+  pub significant: bool,
+  pub result: SimulationResult,
+}
+
+impl Simulator {
+  /// Runs the lineup until the criterion of the test is significant. Looking at the data after
+  /// every batch would make a plain test find differences that are not there, so every look only
+  /// gets the part of the significance level given by an O'Brien-Fleming spending function.
+  pub fn run_sequential(&self, test: &SequentialTest) -> Result<SequentialResult, SimulationError> {
+    let strategies = self.get_distinct_strategies();
+    let strategy_index = |spec: &StrategySpec| {
+      strategies
+        .iter()
+        .position(|strategy| *strategy == spec.to_string())
+        .ok_or_else(|| spec.error("not in the lineup"))
+    };
+    // Strategies are looked up once, so a criterion that does not fit the lineup fails early
+    let measure = match &test.criterion {
+      StoppingCriterion::WinRate(a, b) => Measure::WinRate(strategy_index(a)?, strategy_index(b)?),
+      StoppingCriterion::CardPresence(card) => Measure::CardPresence(*card),
+    };
+
+    let mut accumulator = self.new_accumulator(false);
+    let batch_size = test.batch_size.max(1);
+    let mut game_count = 0;
+    let mut spent = 0.0;
+    let (difference, p_value, significant) = loop {
+      let end = (game_count + batch_size).min(test.max_games);
+      let (batch, _) = self.accumulate_games(game_count..end, false);
+      accumulator = accumulator.merge(batch);
+      game_count = end;
+
+      let (difference, p_value) = match measure {
+        Measure::WinRate(a, b) => win_rate_difference(&accumulator, a, b),
+        Measure::CardPresence(card) => presence_difference(&accumulator, &card),
+      };
+      let fraction = game_count as f64 / test.max_games.max(1) as f64;
+      let spent_now = obrien_fleming_spending(test.significance, fraction);
+      let look_significance = spent_now - spent;
+      spent = spent_now;
+
+      if p_value < look_significance {
+        break (difference, p_value, true);
+      }
+      if game_count >= test.max_games {
+        break (difference, p_value, false);
+      }
+    };
+
+    Ok(SequentialResult {
+      game_count,
+      difference,
+      p_value,
+      significant,
+      result: accumulator.finalize(),
+    })
+  }
+}
+
+/// Win rate of strategy a minus win rate of strategy b, and its p-value
+fn win_rate_difference(accumulator: &SimulationAccumulator, a: usize, b: usize) -> (f64, f64) {
+  let (games_a, wins_a) = accumulator.strategy_wins().games_and_wins(a);
+  let (games_b, wins_b) = accumulator.strategy_wins().games_and_wins(b);
+  let difference = wins_a as f64 / games_a.max(1) as f64 - wins_b as f64 / games_b.max(1) as f64;
+  (
+    difference,
+    two_proportion_p_value(wins_a, games_a, wins_b, games_b),
+  )
+}
+
+/// Presence of the card for the winner minus presence for the other players, and its p-value
+fn presence_difference(accumulator: &SimulationAccumulator, card: &Card) -> (f64, f64) {
+  let (win_present, wins) = accumulator.card_presence().win_presence(card);
+  let (loss_present, losses) = accumulator.card_presence().loss_presence(card);
+  let difference =
+    win_present as f64 / wins.max(1) as f64 - loss_present as f64 / losses.max(1) as f64;
+  (
+    difference,
+    two_proportion_p_value(win_present, wins, loss_present, losses),
+  )
+}
@@ -1,70 +1,492 @@
-use std::collections::HashMap;
-
-use std::sync::Mutex;
+use std::cell::RefCell;
+use std::fmt;
+use std::ops::Range;
+use std::rc::Rc;
 
 use rayon::prelude::*;
-use strum::IntoEnumIterator;
+use strum::{EnumString, IntoStaticStr};
 
 use crate::{
+  config::GameConfig,
   engine::Engine,
-  models::{player::OwnedCard, Card},
-  player_strategies::{GreedyBestCardStrategy, LandmarkRushStrategy, RandomStrategy},
+  error::GameError,
+  events::{GameEvent, GameObserver},
+  game::{Game, GameOutcome},
+  models::player::{DiceRoll, OwnedCard, OwnedLandmark},
+  player_strategies::registry::{StrategyError, StrategyRegistry, StrategySpec},
+  rng::Rng,
+  rules::validation::{IllegalMovePolicy, Violation},
   simulation::accumulator::{SimulationAccumulator, SimulationResult},
+  simulation::export::GameRows,
+  simulation::metric::{DynMetric, Metric},
 };
 
 pub struct GameResult {
+  // Index of the game in its simulation run and the seed it was played with
+  pub game_index: usize,
+  pub seed: u64,
+  // Rounds in which dice were rolled, the last one possibly incomplete
+  pub rounds: usize,
   pub player_cards: Vec<Vec<OwnedCard>>,
-  pub winner_index: usize,
-  pub player_dice_rolls: Vec<Vec<(u8, u8)>>,
+  // Cards every player exchanged or gave away during the game
+  pub player_retired_cards: Vec<Vec<OwnedCard>>,
+  pub player_landmarks: Vec<Vec<OwnedLandmark>>,
+  pub player_coins: Vec<u16>,
+  pub player_forfeited: Vec<bool>,
+  pub outcome: GameOutcome,
+  pub player_dice_rolls: Vec<Vec<DiceRoll>>,
+  pub violations: Vec<Violation>,
+  // Place of every seat at the end of the game, 1 being the best
+  pub placements: Vec<usize>,
+  // Strategy playing in every seat, as an index into the distinct strategies of the lineup
+  pub seat_strategies: Vec<usize>,
+  // All events of the game, only recorded if a metric of the simulator wants them
+  pub events: Vec<GameEvent>,
 }
 
 impl Engine {
-  pub fn collect_data_for_simulation(&self) -> GameResult {
+  pub fn collect_data_for_simulation(&self, seat_strategies: Vec<usize>) -> GameResult {
     GameResult {
+      game_index: 0,
+      seed: self.game.seed,
+      rounds: self
+        .game
+        .players
+        .iter()
+        .filter_map(|player| player.dice_rolls.last())
+        .map(|roll| roll.round + 1)
+        .max()
+        .unwrap_or(0),
       player_cards: self
         .game
         .players
         .iter()
         .map(|player| player.cards.clone())
         .collect(),
-      winner_index: self.game.winner().expect("No winner found"),
+      player_retired_cards: self
+        .game
+        .players
+        .iter()
+        .map(|player| player.retired_cards.clone())
+        .collect(),
+      player_landmarks: self
+        .game
+        .players
+        .iter()
+        .map(|player| player.landmarks.clone())
+        .collect(),
+      player_coins: self
+        .game
+        .players
+        .iter()
+        .map(|player| player.coins)
+        .collect(),
+      player_forfeited: self
+        .game
+        .players
+        .iter()
+        .map(|player| player.forfeited)
+        .collect(),
+      outcome: self.game.outcome().expect("Game is not finished"),
       player_dice_rolls: self
         .game
         .players
         .iter()
         .map(|player| player.dice_rolls.clone())
         .collect(),
+      violations: self.referee.violations.clone(),
+      placements: self.game.placements(),
+      seat_strategies,
+      events: Vec::new(),
     }
   }
 }
 
-const NUM_PLAYERS: usize = 4;
+/// Errors of setting up and playing the games of a simulation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SimulationError {
+  Game(GameError),
+  Strategy(StrategyError),
+}
+
+impl fmt::Display for SimulationError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      SimulationError::Game(error) => write!(f, "{}", error),
+      SimulationError::Strategy(error) => write!(f, "{}", error),
+    }
+  }
+}
 
-pub struct Simulator;
+impl std::error::Error for SimulationError {}
+
+impl From<GameError> for SimulationError {
+  fn from(error: GameError) -> Self {
+    SimulationError::Game(error)
+  }
+}
+
+impl From<StrategyError> for SimulationError {
+  fn from(error: StrategyError) -> Self {
+    SimulationError::Strategy(error)
+  }
+}
+
+/// How the lineup is seated in each game
+#[derive(Clone, Copy, Debug, PartialEq, Eq, EnumString, IntoStaticStr)]
+#[strum(ascii_case_insensitive)]
+pub enum SeatingMode {
+  // Every strategy always plays in the seat of its position in the lineup
+  Fixed,
+  // The lineup moves one seat further every game
+  Rotate,
+  // The games cycle through every permutation of the lineup
+  Permute,
+}
+
+#[derive(Clone)]
+pub struct Simulator {
+  config: GameConfig,
+  illegal_move_policy: IllegalMovePolicy,
+  // Strategy of every seat, the number of players is the length of the lineup
+  lineup: Vec<StrategySpec>,
+  registry: StrategyRegistry,
+  seating: SeatingMode,
+  // All game and strategy seeds are derived from this seed
+  master_seed: u64,
+  // Metrics observed in addition to the built-in ones
+  metrics: Vec<Box<dyn DynMetric>>,
+}
+
+impl Default for Simulator {
+  fn default() -> Self {
+    Self::new()
+  }
+}
 
 impl Simulator {
+  /// Creates a simulator with a random master seed
   pub fn new() -> Self {
-    Self {}
+    Self {
+      config: GameConfig::default(),
+      illegal_move_policy: IllegalMovePolicy::default(),
+      lineup: ["random", "landmark", "random", "greedy"]
+        .iter()
+        .map(|name| StrategySpec::new(name))
+        .collect(),
+      registry: StrategyRegistry::with_builtin_strategies(),
+      seating: SeatingMode::Fixed,
+      master_seed: Rng::new().get_seed(),
+      metrics: Vec::new(),
+    }
+  }
+
+  /// Set the master seed. Running the same simulation with the same master seed always yields
+  /// the same result.
+  pub fn set_master_seed(&mut self, master_seed: u64) {
+    self.master_seed = master_seed;
+  }
+
+  pub fn get_master_seed(&self) -> u64 {
+    self.master_seed
+  }
+
+  /// Set the game rules used in all of the games
+  pub fn set_config(&mut self, config: GameConfig) {
+    self.config = config;
+  }
+
+  /// Set what happens when a strategy makes an illegal decision in any of the games
+  pub fn set_illegal_move_policy(&mut self, policy: IllegalMovePolicy) {
+    self.illegal_move_policy = policy;
+  }
+
+  /// Set the registry used to build the strategies of the lineup. Set it before the lineup.
+  pub fn set_registry(&mut self, registry: StrategyRegistry) {
+    self.registry = registry;
+  }
+
+  /// Set the strategies playing in every game, one per seat. Fails if the number of players is
+  /// not allowed by the rules or a spec does not build.
+  pub fn set_lineup(&mut self, lineup: Vec<StrategySpec>) -> Result<(), SimulationError> {
+    if lineup.len() < self.config.min_players || lineup.len() > self.config.max_players {
+      return Err(GameError::InvalidPlayerCount(lineup.len()).into());
+    }
+    for spec in lineup.iter() {
+      self.registry.build(spec, 0)?;
+    }
+    self.lineup = lineup;
+    Ok(())
+  }
+
+  pub fn get_lineup(&self) -> &[StrategySpec] {
+    &self.lineup
+  }
+
+  /// Set how the lineup is seated. Rotating or permuting the lineup cancels out seat effects
+  /// when comparing strategies.
+  pub fn set_seating(&mut self, seating: SeatingMode) {
+    self.seating = seating;
+  }
+
+  /// Distinct strategies of the lineup in the order they first appear
+  pub fn get_distinct_strategies(&self) -> Vec<String> {
+    let mut strategies: Vec<String> = Vec::new();
+    for spec in self.lineup.iter() {
+      let name = spec.to_string();
+      if !strategies.contains(&name) {
+        strategies.push(name);
+      }
+    }
+    strategies
+  }
+
+  /// Position in the lineup of the strategy in every seat of the given game
+  pub fn get_seating(&self, game_index: usize) -> Vec<usize> {
+    let num_players = self.lineup.len();
+    match self.seating {
+      SeatingMode::Fixed => (0..num_players).collect(),
+      SeatingMode::Rotate => (0..num_players)
+        .map(|seat| (seat + game_index) % num_players)
+        .collect(),
+      SeatingMode::Permute => {
+        // Decode the game index as the n-th permutation in lexicographic order
+        let permutation_count: usize = (1..=num_players).product();
+        let mut rest = game_index % permutation_count;
+        let mut positions: Vec<usize> = (0..num_players).collect();
+        let mut seating = Vec::with_capacity(num_players);
+        for remaining in (1..=num_players).rev() {
+          let block: usize = (1..remaining).product();
+          seating.push(positions.remove(rest / block));
+          rest %= block;
+        }
+        seating
+      }
+    }
+  }
+
+  /// Add a metric to every run. Its report is added as a section to the simulation result.
+  pub fn add_metric<M: Metric>(&mut self, metric: M) {
+    self.metrics.push(Box::new(metric));
   }
 
   pub fn run(&self, sim_count: usize) -> SimulationResult {
-    let mut accumulator = SimulationAccumulator::new();
+    let (accumulator, _) = self.accumulate_games(0..sim_count, false);
+    accumulator.finalize()
+  }
 
-    // Run simulations in parallel
-    (0..sim_count).into_par_iter().for_each(|_| {
-      let mut engine = Engine::new();
+  /// Like `run`, but also returns a row for every seat of every game, in game order. If a metric
+  /// wants the events of the games, the coin flow of every game is returned as well.
+  pub fn run_with_game_rows(&self, sim_count: usize) -> (SimulationResult, GameRows) {
+    let (mut accumulator, _) = self.accumulate_games(0..sim_count, true);
+    let mut rows = accumulator.game_rows.take().unwrap_or_default();
+    rows.sort();
+    (accumulator.finalize(), rows)
+  }
 
-      engine.add_player_strategy(Box::new(RandomStrategy::new()));
-      engine.add_player_strategy(Box::new(LandmarkRushStrategy::new()));
-      engine.add_player_strategy(Box::new(RandomStrategy::new()));
-      // engine.add_player_strategy(Box::new(RandomStrategy::new()));
-      engine.add_player_strategy(Box::new(GreedyBestCardStrategy::new()));
-      engine.run();
+  /// Accumulator for the games of this simulator, with empty copies of its metrics
+  pub(crate) fn new_accumulator(&self, keep_game_rows: bool) -> SimulationAccumulator {
+    let mut accumulator = SimulationAccumulator::new(
+      self.lineup.len(),
+      self.get_distinct_strategies(),
+      self.metrics.clone(),
+    );
+    if keep_game_rows {
+      accumulator.game_rows = Some(GameRows::default());
+    }
+    accumulator
+  }
+
+  /// Plays the games with the given indices in parallel. Every rayon job accumulates its games
+  /// on its own and the partial results are merged, so games never wait for each other. Returns
+  /// the accumulated games and the number of failed games.
+  pub(crate) fn accumulate_games(
+    &self,
+    game_indices: Range<usize>,
+    keep_game_rows: bool,
+  ) -> (SimulationAccumulator, usize) {
+    let empty = || (self.new_accumulator(keep_game_rows), 0);
+    game_indices
+      .into_par_iter()
+      .fold(empty, |(mut accumulator, failed_count), game_index| {
+        // A failed game is skipped so that one illegal decision does not abort the whole batch
+        match self.play_game(game_index) {
+          Ok(result) => {
+            accumulator.accumulate(&result);
+            (accumulator, failed_count)
+          }
+          Err(error) => {
+            eprintln!("Game {} failed: {}", game_index, error);
+            (accumulator, failed_count + 1)
+          }
+        }
+      })
+      .reduce(empty, |(a, failed_a), (b, failed_b)| {
+        (a.merge(b), failed_a + failed_b)
+      })
+  }
 
-      let result = engine.collect_data_for_simulation();
-      accumulator.accumulate(&result);
-    });
+  /// Plays the games in parallel and returns the result of every game in game order. Failed
+  /// games are reported and left out.
+  pub fn play_games(&self, sim_count: usize) -> Vec<GameResult> {
+    let results: Vec<Result<GameResult, SimulationError>> = (0..sim_count)
+      .into_par_iter()
+      .map(|game_index| self.play_game(game_index))
+      .collect();
+    results
+      .into_iter()
+      .enumerate()
+      .filter_map(|(game_index, result)| match result {
+        Ok(result) => Some(result),
+        Err(error) => {
+          eprintln!("Game {} failed: {}", game_index, error);
+          None
+        }
+      })
+      .collect()
+  }
+
+  /// Plays the game with the given index to the end
+  pub fn play_game(&self, game_index: usize) -> Result<GameResult, SimulationError> {
+    let mut engine = self.build_engine(game_index)?;
+    let events = Rc::new(RefCell::new(Vec::new()));
+    if self.metrics.iter().any(|metric| metric.wants_events()) {
+      engine.add_observer(Box::new(EventLog(events.clone())));
+    }
+    engine.run()?;
+
+    let strategies = self.get_distinct_strategies();
+    let seat_strategies = self
+      .get_seating(game_index)
+      .iter()
+      .map(|position| {
+        let name = self.lineup[*position].to_string();
+        strategies
+          .iter()
+          .position(|strategy| *strategy == name)
+          .unwrap()
+      })
+      .collect();
+    let mut result = engine.collect_data_for_simulation(seat_strategies);
+    result.game_index = game_index;
+    result.events = events.take();
+    Ok(result)
+  }
+
+  /// Sets up the game with the given index exactly as it is played in a simulation run, so that
+  /// any game can be re-run on its own. Seeds only depend on the master seed and the game index.
+  pub fn build_engine(&self, game_index: usize) -> Result<Engine, SimulationError> {
+    let game_seed = Rng::derive_seed(self.master_seed, game_index as u64);
+    let mut engine = Engine::new(self.config.clone(), Some(game_seed));
+    engine.set_illegal_move_policy(self.illegal_move_policy);
+
+    for (seat, position) in self.get_seating(game_index).iter().enumerate() {
+      let seat_seed = Rng::derive_seed(game_seed, seat as u64);
+      let spec = &self.lineup[*position];
+      engine.add_player_strategy_with_spec(self.registry.build(spec, seat_seed)?, spec.clone());
+    }
+    Ok(engine)
+  }
+}
+
+// Keeps the events of a game for the metrics
+struct EventLog(Rc<RefCell<Vec<GameEvent>>>);
+
+impl GameObserver for EventLog {
+  fn on_event(&mut self, event: &GameEvent, _game: &Game) {
+    self.0.borrow_mut().push(event.clone());
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn simulator(lineup: &[&str], seating: SeatingMode) -> Simulator {
+    let mut simulator = Simulator::new();
+    simulator.set_master_seed(9);
+    simulator.set_seating(seating);
+    simulator
+      .set_lineup(lineup.iter().map(|text| text.parse().unwrap()).collect())
+      .unwrap();
+    simulator
+  }
+
+  #[test]
+  fn fixed_seating_keeps_the_lineup_order() {
+    let simulator = simulator(&["random", "greedy", "landmark"], SeatingMode::Fixed);
+    for game_index in 0..5 {
+      assert_eq!(simulator.get_seating(game_index), vec![0, 1, 2]);
+    }
+  }
+
+  #[test]
+  fn rotate_seating_moves_one_seat_per_game() {
+    let simulator = simulator(&["random", "greedy", "landmark"], SeatingMode::Rotate);
+    assert_eq!(simulator.get_seating(0), vec![0, 1, 2]);
+    assert_eq!(simulator.get_seating(1), vec![1, 2, 0]);
+    assert_eq!(simulator.get_seating(2), vec![2, 0, 1]);
+    assert_eq!(simulator.get_seating(3), vec![0, 1, 2]);
+  }
+
+  #[test]
+  fn permute_seating_cycles_through_every_permutation_in_order() {
+    let lineup = ["random", "greedy", "landmark", "optimized"];
+    let simulator = simulator(&lineup, SeatingMode::Permute);
+    let seatings: Vec<Vec<usize>> = (0..24)
+      .map(|game_index| simulator.get_seating(game_index))
+      .collect();
+    assert_eq!(seatings[0], vec![0, 1, 2, 3]);
+    assert_eq!(seatings[1], vec![0, 1, 3, 2]);
+    assert_eq!(seatings[6], vec![1, 0, 2, 3]);
+    assert_eq!(seatings[23], vec![3, 2, 1, 0]);
+    let mut sorted = seatings.clone();
+    sorted.sort();
+    assert_eq!(sorted, seatings);
+    sorted.dedup();
+    assert_eq!(sorted.len(), 24);
+    assert_eq!(simulator.get_seating(24), seatings[0]);
+  }
+
+  #[test]
+  fn set_lineup_rejects_bad_lineups() {
+    let mut simulator = Simulator::new();
+    assert_eq!(
+      simulator.set_lineup(vec![StrategySpec::new("random")]),
+      Err(SimulationError::Game(GameError::InvalidPlayerCount(1)))
+    );
+    assert_eq!(
+      simulator.set_lineup(vec![
+        StrategySpec::new("random"),
+        StrategySpec::new("nobody")
+      ]),
+      Err(SimulationError::Strategy(StrategyError::UnknownStrategy(
+        "nobody".to_string()
+      )))
+    );
+  }
+
+  #[test]
+  fn distinct_strategies_keep_their_first_position() {
+    let simulator = simulator(
+      &["greedy", "random", "greedy", "random:seed=1"],
+      SeatingMode::Fixed,
+    );
+    assert_eq!(
+      simulator.get_distinct_strategies(),
+      vec!["greedy", "random", "random:seed=1"]
+    );
+  }
 
-    accumulator.finalize(sim_count, NUM_PLAYERS)
+  #[test]
+  fn games_only_depend_on_the_master_seed_and_index() {
+    let simulator = simulator(&["random", "greedy", "landmark"], SeatingMode::Permute);
+    let first = simulator.play_game(5).unwrap();
+    let again = simulator.play_game(5).unwrap();
+    assert_eq!(first.seed, again.seed);
+    assert_eq!(first.outcome, again.outcome);
+    assert_eq!(first.player_coins, again.player_coins);
+    assert_eq!(first.seat_strategies, again.seat_strategies);
+    assert_ne!(simulator.play_game(6).unwrap().seed, first.seed);
   }
 }
@@ -0,0 +1,163 @@
+use serde::{Deserialize, Serialize};
+
+use crate::player_strategies::registry::StrategySpec;
+use crate::record::RecordError;
+use crate::rng::Rng;
+use crate::simulation::rating::{update_ratings, Rating};
+use crate::simulation::simulator::{GameResult, SimulationError, Simulator};
+
+/// Results of one strategy over all games it played in a tournament
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Standing {
+  pub strategy: StrategySpec,
+  pub rating: Rating,
+  pub games: usize,
+  pub wins: usize,
+  pub draws: usize,
+  // Sum of the places of all games, 1 being the best
+  pub total_placement: usize,
+}
+
+impl Standing {
+  pub fn win_rate(&self) -> f64 {
+    self.wins as f64 / self.games.max(1) as f64
+  }
+
+  pub fn average_placement(&self) -> f64 {
+    self.total_placement as f64 / self.games.max(1) as f64
+  }
+}
+
+/// Leaderboard of a tournament, sorted by conservative rating, best first. Saved so that the
+/// ratings of later runs can be compared as strategies change, and loaded from JSON.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct TournamentResult {
+  pub master_seed: u64,
+  pub table_sizes: Vec<usize>,
+  pub games_per_lineup: usize,
+  pub lineup_count: usize,
+  pub standings: Vec<Standing>,
+}
+
+impl TournamentResult {
+  pub fn load(path: &str) -> Result<Self, RecordError> {
+    Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?)
+  }
+}
+
+/// All lineups of the given size made of different strategies. Lineups are combinations, so the
+/// seat order is that of the strategies.
+pub fn round_robin_lineups(
+  strategies: &[StrategySpec],
+  players_per_game: usize,
+) -> Vec<Vec<StrategySpec>> {
+  if players_per_game == 0 {
+    return vec![Vec::new()];
+  }
+  let mut lineups = Vec::new();
+  for (index, strategy) in strategies.iter().enumerate() {
+    for mut rest in round_robin_lineups(&strategies[index + 1..], players_per_game - 1) {
+      rest.insert(0, strategy.clone());
+      lineups.push(rest);
+    }
+  }
+  lineups
+}
+
+impl Simulator {
+  /// Plays every round robin lineup of the strategies at each of the table sizes, with the rules,
+  /// policy and seating of this simulator. Every lineup gets its own master seed derived from
+  /// the simulator's. Ratings are updated from the placements of every game, taking the games of
+  /// all lineups in turn so that the order of the lineups does not matter.
+  pub fn run_tournament(
+    &self,
+    strategies: &[StrategySpec],
+    table_sizes: &[usize],
+    games_per_lineup: usize,
+  ) -> Result<TournamentResult, SimulationError> {
+    let mut standings: Vec<Standing> = strategies
+      .iter()
+      .map(|strategy| Standing {
+        strategy: strategy.clone(),
+        rating: Rating::default(),
+        games: 0,
+        wins: 0,
+        draws: 0,
+        total_placement: 0,
+      })
+      .collect();
+
+    let lineups: Vec<Vec<StrategySpec>> = table_sizes
+      .iter()
+      .flat_map(|players| round_robin_lineups(strategies, *players))
+      .collect();
+
+    // Results of every lineup, with the standing index of every seat of each game
+    let mut lineup_results: Vec<Vec<(GameResult, Vec<usize>)>> = Vec::new();
+    for (lineup_index, lineup) in lineups.iter().enumerate() {
+      let mut simulator = self.clone();
+      simulator.set_master_seed(Rng::derive_seed(
+        self.get_master_seed(),
+        lineup_index as u64,
+      ));
+      simulator.set_lineup(lineup.clone())?;
+      let distinct_strategies = simulator.get_distinct_strategies();
+      let results = simulator
+        .play_games(games_per_lineup)
+        .into_iter()
+        .map(|result| {
+          let seat_standings = result
+            .seat_strategies
+            .iter()
+            .map(|strategy| {
+              standings
+                .iter()
+                .position(|standing| {
+                  standing.strategy.to_string() == distinct_strategies[*strategy]
+                })
+                .unwrap()
+            })
+            .collect();
+          (result, seat_standings)
+        })
+        .collect();
+      lineup_results.push(results);
+    }
+
+    for game_index in 0..games_per_lineup {
+      for results in lineup_results.iter() {
+        let Some((result, seat_standings)) = results.get(game_index) else {
+          continue;
+        };
+        for (seat, standing_index) in seat_standings.iter().enumerate() {
+          let standing = &mut standings[*standing_index];
+          standing.games += 1;
+          standing.total_placement += result.placements[seat];
+          match result.outcome.winner() {
+            Some(winner) if winner == seat => standing.wins += 1,
+            Some(_) => {}
+            None => standing.draws += 1,
+          }
+        }
+
+        let mut ratings: Vec<Rating> = seat_standings
+          .iter()
+          .map(|standing_index| standings[*standing_index].rating)
+          .collect();
+        update_ratings(&mut ratings, &result.placements);
+        for (standing_index, rating) in seat_standings.iter().zip(ratings) {
+          standings[*standing_index].rating = rating;
+        }
+      }
+    }
+
+    standings.sort_by(|a, b| b.rating.conservative().total_cmp(&a.rating.conservative()));
+    Ok(TournamentResult {
+      master_seed: self.get_master_seed(),
+      table_sizes: table_sizes.to_vec(),
+      games_per_lineup,
+      lineup_count: lineups.len(),
+      standings,
+    })
+  }
+}
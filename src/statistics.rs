@@ -0,0 +1,397 @@
+//! Confidence intervals and significance tests for the rates measured by simulations
+
+use std::fmt;
+
+use serde::Serialize;
+
+/// Confidence level of all intervals
+pub const CONFIDENCE_LEVEL: f64 = 0.95;
+/// Differences with a p-value below this are reported as significant
+pub const SIGNIFICANCE_LEVEL: f64 = 0.05;
+// Two-sided standard normal quantile of the confidence level
+const Z: f64 = 1.959963984540054;
+
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct ConfidenceInterval {
+  pub low: f64,
+  pub high: f64,
+}
+
+impl ConfidenceInterval {
+  pub fn contains(&self, value: f64) -> bool {
+    self.low <= value && value <= self.high
+  }
+
+  /// The interval of a rate as percentages
+  pub fn to_percent_string(&self) -> String {
+    format!("{:.1}-{:.1}%", self.low * 100.0, self.high * 100.0)
+  }
+}
+
+impl fmt::Display for ConfidenceInterval {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.pad(&format!("{:.3}-{:.3}", self.low, self.high))
+  }
+}
+
+/// Sums of paired observations to compute their Pearson correlation. Sums of different batches
+/// can be merged, so the pairs do not have to be kept.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CorrelationSums {
+  count: usize,
+  x: f64,
+  y: f64,
+  xx: f64,
+  yy: f64,
+  xy: f64,
+}
+
+impl CorrelationSums {
+  pub fn add(&mut self, x: f64, y: f64) {
+    self.count += 1;
+    self.x += x;
+    self.y += y;
+    self.xx += x * x;
+    self.yy += y * y;
+    self.xy += x * y;
+  }
+
+  pub fn merge(&mut self, other: &CorrelationSums) {
+    self.count += other.count;
+    self.x += other.x;
+    self.y += other.y;
+    self.xx += other.xx;
+    self.yy += other.yy;
+    self.xy += other.xy;
+  }
+
+  /// Pearson correlation coefficient, None if either variable never varied
+  pub fn coefficient(&self) -> Option<f64> {
+    let n = self.count as f64;
+    let covariance = n * self.xy - self.x * self.y;
+    let variance_x = n * self.xx - self.x * self.x;
+    let variance_y = n * self.yy - self.y * self.y;
+    // Both variances are scaled by n², rounding leaves a small residue for constant variables
+    let min_variance = 1e-9 * n * n;
+    if variance_x <= min_variance || variance_y <= min_variance {
+      return None;
+    }
+    Some((covariance / (variance_x * variance_y).sqrt()).clamp(-1.0, 1.0))
+  }
+}
+
+/// Significant p-values are marked with a star
+pub fn format_p_value(p_value: f64) -> String {
+  if p_value < SIGNIFICANCE_LEVEL {
+    format!("{:.3}*", p_value)
+  } else {
+    format!("{:.3}", p_value)
+  }
+}
+
+/// Wilson score interval of a rate of successes out of trials. Unlike the normal approximation
+/// it stays within [0, 1] and works for rates close to 0 or 1. Without trials the rate can be
+/// anything.
+pub fn wilson_interval(successes: usize, trials: usize) -> ConfidenceInterval {
+  if trials == 0 {
+    return ConfidenceInterval {
+      low: 0.0,
+      high: 1.0,
+    };
+  }
+  let n = trials as f64;
+  let p = successes as f64 / n;
+  let z2 = Z * Z;
+  let denominator = 1.0 + z2 / n;
+  let center = (p + z2 / (2.0 * n)) / denominator;
+  let margin = Z / denominator * (p * (1.0 - p) / n + z2 / (4.0 * n * n)).sqrt();
+  ConfidenceInterval {
+    low: (center - margin).max(0.0),
+    high: (center + margin).min(1.0),
+  }
+}
+
+/// Two-sided p-value of the two proportion z test that both rates are the same
+pub fn two_proportion_p_value(
+  successes_a: usize,
+  trials_a: usize,
+  successes_b: usize,
+  trials_b: usize,
+) -> f64 {
+  if trials_a == 0 || trials_b == 0 {
+    return 1.0;
+  }
+  let (n_a, n_b) = (trials_a as f64, trials_b as f64);
+  let pooled = (successes_a + successes_b) as f64 / (n_a + n_b);
+  let standard_error = (pooled * (1.0 - pooled) * (1.0 / n_a + 1.0 / n_b)).sqrt();
+  if standard_error == 0.0 {
+    return 1.0;
+  }
+  let z = (successes_a as f64 / n_a - successes_b as f64 / n_b) / standard_error;
+  erfc(z.abs() / std::f64::consts::SQRT_2)
+}
+
+/// Chi-square statistic and p-value of the goodness of fit test that every category is equally
+/// likely, e.g. that every seat wins as often
+pub fn chi_square_uniform_test(counts: &[usize]) -> (f64, f64) {
+  let total: usize = counts.iter().sum();
+  if total == 0 || counts.len() < 2 {
+    return (0.0, 1.0);
+  }
+  let expected = total as f64 / counts.len() as f64;
+  let statistic: f64 = counts
+    .iter()
+    .map(|count| (*count as f64 - expected).powi(2) / expected)
+    .sum();
+  let degrees_of_freedom = (counts.len() - 1) as f64;
+  (
+    statistic,
+    upper_incomplete_gamma(degrees_of_freedom / 2.0, statistic / 2.0),
+  )
+}
+
+/// Cumulative distribution function of the standard normal distribution
+pub fn normal_cdf(x: f64) -> f64 {
+  0.5 * erfc(-x / std::f64::consts::SQRT_2)
+}
+
+/// Quantile function of the standard normal distribution, the inverse of `normal_cdf`
+pub fn normal_quantile(p: f64) -> f64 {
+  // Bisection is plenty fast for the few quantiles a simulation needs
+  let (mut low, mut high) = (-40.0, 40.0);
+  for _ in 0..100 {
+    let middle = 0.5 * (low + high);
+    if normal_cdf(middle) < p {
+      low = middle;
+    } else {
+      high = middle;
+    }
+  }
+  0.5 * (low + high)
+}
+
+/// O'Brien-Fleming type alpha spending function of Lan and DeMets. Gives the part of the
+/// significance level that may be used up once the given fraction of the maximum sample has
+/// been seen. Little is spent early, so only large differences stop a sequential test early.
+pub fn obrien_fleming_spending(significance: f64, fraction: f64) -> f64 {
+  if fraction <= 0.0 {
+    return 0.0;
+  }
+  let z = normal_quantile(1.0 - significance / 2.0);
+  (2.0 - 2.0 * normal_cdf(z / fraction.min(1.0).sqrt())).min(significance)
+}
+
+/// Complementary error function, with a fractional error below 1.2e-7
+fn erfc(x: f64) -> f64 {
+  let z = x.abs();
+  let t = 1.0 / (1.0 + 0.5 * z);
+  let value = t
+    * (-z * z - 1.26551223
+      + t
+        * (1.00002368
+          + t
+            * (0.37409196
+              + t
+                * (0.09678418
+                  + t
+                    * (-0.18628806
+                      + t
+                        * (0.27886807
+                          + t
+                            * (-1.13520398
+                              + t * (1.48851587 + t * (-0.82215223 + t * 0.17087277)))))))))
+      .exp();
+  if x >= 0.0 {
+    value
+  } else {
+    2.0 - value
+  }
+}
+
+/// Natural logarithm of the gamma function (Lanczos approximation)
+fn ln_gamma(x: f64) -> f64 {
+  const COEFFICIENTS: [f64; 6] = [
+    76.18009172947146,
+    -86.50532032941677,
+    24.01409824083091,
+    -1.231739572450155,
+    0.1208650973866179e-2,
+    -0.5395239384953e-5,
+  ];
+  let mut y = x;
+  let tmp = x + 5.5;
+  let tmp = tmp - (x + 0.5) * tmp.ln();
+  let mut series = 1.000000000190015;
+  for coefficient in COEFFICIENTS {
+    y += 1.0;
+    series += coefficient / y;
+  }
+  -tmp + (2.5066282746310005 * series / x).ln()
+}
+
+/// Regularized upper incomplete gamma function Q(a, x), by its series for small x and by its
+/// continued fraction otherwise
+fn upper_incomplete_gamma(a: f64, x: f64) -> f64 {
+  const MAX_ITERATIONS: usize = 500;
+  const EPSILON: f64 = 1e-14;
+  const MIN_FLOAT: f64 = 1e-300;
+
+  if x <= 0.0 {
+    return 1.0;
+  }
+  let log_prefactor = -x + a * x.ln() - ln_gamma(a);
+  if x < a + 1.0 {
+    let mut term = 1.0 / a;
+    let mut sum = term;
+    let mut denominator = a;
+    for _ in 0..MAX_ITERATIONS {
+      denominator += 1.0;
+      term *= x / denominator;
+      sum += term;
+      if term.abs() < sum.abs() * EPSILON {
+        break;
+      }
+    }
+    (1.0 - sum * log_prefactor.exp()).max(0.0)
+  } else {
+    let mut b = x + 1.0 - a;
+    let mut c = 1.0 / MIN_FLOAT;
+    let mut d = 1.0 / b;
+    let mut h = d;
+    for i in 1..MAX_ITERATIONS {
+      let an = -(i as f64) * (i as f64 - a);
+      b += 2.0;
+      d = an * d + b;
+      if d.abs() < MIN_FLOAT {
+        d = MIN_FLOAT;
+      }
+      c = b + an / c;
+      if c.abs() < MIN_FLOAT {
+        c = MIN_FLOAT;
+      }
+      d = 1.0 / d;
+      let delta = d * c;
+      h *= delta;
+      if (delta - 1.0).abs() < EPSILON {
+        break;
+      }
+    }
+    log_prefactor.exp() * h
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn assert_close(actual: f64, expected: f64, tolerance: f64) {
+    assert!(
+      (actual - expected).abs() < tolerance,
+      "expected {}, got {}",
+      expected,
+      actual
+    );
+  }
+
+  #[test]
+  fn wilson_interval_matches_known_values() {
+    let interval = wilson_interval(5, 10);
+    assert_close(interval.low, 0.236593, 1e-6);
+    assert_close(interval.high, 0.763407, 1e-6);
+
+    let interval = wilson_interval(81, 263);
+    assert_close(interval.low, 0.255289, 1e-6);
+    assert_close(interval.high, 0.366210, 1e-6);
+  }
+
+  #[test]
+  fn wilson_interval_stays_within_bounds() {
+    let interval = wilson_interval(0, 10);
+    assert_close(interval.low, 0.0, 1e-12);
+    assert_close(interval.high, 0.277533, 1e-6);
+
+    let interval = wilson_interval(10, 10);
+    assert_close(interval.low, 0.722467, 1e-6);
+    assert_close(interval.high, 1.0, 1e-12);
+
+    let interval = wilson_interval(0, 0);
+    assert_eq!((interval.low, interval.high), (0.0, 1.0));
+  }
+
+  #[test]
+  fn two_proportion_p_value_matches_known_values() {
+    assert_close(two_proportion_p_value(50, 100, 30, 100), 0.003892, 1e-5);
+    assert_close(two_proportion_p_value(30, 100, 50, 100), 0.003892, 1e-5);
+    assert_close(two_proportion_p_value(40, 100, 40, 100), 1.0, 1e-6);
+    assert_eq!(two_proportion_p_value(0, 0, 5, 10), 1.0);
+    assert_eq!(two_proportion_p_value(0, 10, 0, 10), 1.0);
+  }
+
+  #[test]
+  fn chi_square_uniform_test_matches_known_values() {
+    let (statistic, p_value) = chi_square_uniform_test(&[10, 20, 30]);
+    assert_close(statistic, 10.0, 1e-9);
+    // Two degrees of freedom, so the p-value is exp(-statistic / 2)
+    assert_close(p_value, (-5.0f64).exp(), 1e-9);
+
+    let (statistic, p_value) = chi_square_uniform_test(&[25, 25, 25, 25]);
+    assert_eq!(statistic, 0.0);
+    assert_close(p_value, 1.0, 1e-9);
+
+    assert_eq!(chi_square_uniform_test(&[]), (0.0, 1.0));
+    assert_eq!(chi_square_uniform_test(&[7]), (0.0, 1.0));
+  }
+
+  #[test]
+  fn normal_quantile_inverts_normal_cdf() {
+    assert_close(normal_cdf(0.0), 0.5, 1e-7);
+    assert_close(normal_cdf(Z), 0.975, 1e-7);
+    assert_close(normal_quantile(0.975), Z, 1e-6);
+    for p in [0.01, 0.2, 0.5, 0.8, 0.999] {
+      assert_close(normal_cdf(normal_quantile(p)), p, 1e-7);
+    }
+  }
+
+  #[test]
+  fn obrien_fleming_spending_spends_little_early() {
+    assert_eq!(obrien_fleming_spending(0.05, 0.0), 0.0);
+    assert_close(obrien_fleming_spending(0.05, 0.5), 0.005575, 1e-5);
+    assert_close(obrien_fleming_spending(0.05, 1.0), 0.05, 1e-6);
+    assert!(obrien_fleming_spending(0.05, 2.0) <= 0.05);
+  }
+
+  #[test]
+  fn correlation_of_merged_sums() {
+    let mut first = CorrelationSums::default();
+    let mut second = CorrelationSums::default();
+    for x in 0..5 {
+      first.add(x as f64, 2.0 * x as f64 + 1.0);
+      second.add(x as f64, -(x as f64));
+    }
+    assert_close(first.coefficient().unwrap(), 1.0, 1e-9);
+    assert_close(second.coefficient().unwrap(), -1.0, 1e-9);
+
+    let mut merged = first;
+    merged.merge(&second);
+    let mut direct = CorrelationSums::default();
+    for x in 0..5 {
+      direct.add(x as f64, 2.0 * x as f64 + 1.0);
+      direct.add(x as f64, -(x as f64));
+    }
+    assert_close(
+      merged.coefficient().unwrap(),
+      direct.coefficient().unwrap(),
+      1e-12,
+    );
+  }
+
+  #[test]
+  fn correlation_of_constant_variable_is_none() {
+    let mut sums = CorrelationSums::default();
+    for x in 0..5 {
+      sums.add(x as f64, 3.0);
+    }
+    assert_eq!(sums.coefficient(), None);
+    assert_eq!(CorrelationSums::default().coefficient(), None);
+  }
+}